@@ -0,0 +1,406 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rusqlite::Connection;
+use serde::Deserialize;
+
+use crate::error::DeckError;
+use crate::format::DeckFormat;
+use crate::Card;
+
+/// Collection files in the order they are looked for. Newer Anki releases
+/// also write a zstd compressed `collection.anki21b`, which is not supported;
+/// exporting with "Support older Anki versions" ticked includes a readable
+/// copy.
+const COLLECTION_NAMES: [&str; 2] = ["collection.anki21", "collection.anki2"];
+
+/// Which note fields become the front and back for one note type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMapping {
+    pub note_type: String,
+    pub front: String,
+    pub back: String,
+}
+
+/// Parses a mapping written as `Note type=Front field,Back field`.
+pub fn parse_mapping(input: &str) -> Option<FieldMapping> {
+    let (note_type, fields) = input.split_once('=')?;
+    let (front, back) = fields.split_once(',')?;
+
+    Some(FieldMapping {
+        note_type: note_type.trim().to_string(),
+        front: front.trim().to_string(),
+        back: back.trim().to_string(),
+    })
+}
+
+#[derive(Deserialize)]
+struct NoteType {
+    name: String,
+    flds: Vec<NoteField>,
+}
+
+#[derive(Deserialize)]
+struct NoteField {
+    name: String,
+    ord: usize,
+}
+
+fn import_error(message: impl ToString) -> DeckError {
+    DeckError::Import {
+        path: None,
+        message: message.to_string(),
+    }
+}
+
+/// Reads the notes of an `.apkg` archive as cards.
+///
+/// Note types without a mapping use their first field as the front and their
+/// second as the back. The note's guid becomes the card id, so progress
+/// survives later edits made in Anki.
+pub fn load_from_apkg(
+    reader: impl Read + Seek,
+    mappings: &[FieldMapping],
+) -> Result<Vec<Card>, DeckError> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(import_error)?;
+
+    let name = COLLECTION_NAMES
+        .iter()
+        .find(|name| archive.index_for_name(name).is_some())
+        .ok_or_else(|| import_error("archive has no collection.anki2 or collection.anki21 file"))?;
+
+    // SQLite needs a real file to open, so the collection is unpacked first
+    static IMPORTS: AtomicUsize = AtomicUsize::new(0);
+    let collection_path = std::env::temp_dir().join(format!(
+        "flashcards-{}-{}-{name}",
+        std::process::id(),
+        IMPORTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut collection = archive.by_name(name).map_err(import_error)?;
+    std::io::copy(
+        &mut collection,
+        &mut std::fs::File::create(&collection_path)?,
+    )?;
+
+    let cards = load_collection(&collection_path, mappings);
+    let _ = std::fs::remove_file(&collection_path);
+
+    cards
+}
+
+/// Anki packages as a [`DeckFormat`]. They can only be read.
+pub struct AnkiFormat {
+    mappings: Vec<FieldMapping>,
+}
+
+impl AnkiFormat {
+    pub fn new(mappings: Vec<FieldMapping>) -> Self {
+        Self { mappings }
+    }
+}
+
+impl DeckFormat for AnkiFormat {
+    fn load(&self, reader: &mut dyn Read) -> Result<Vec<Card>, DeckError> {
+        // Zip archives are read from the end, so the package is buffered
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        load_from_apkg(Cursor::new(data), &self.mappings)
+    }
+
+    fn write(&self, _cards: &[Card], _writer: &mut dyn Write) -> Result<(), DeckError> {
+        Err(import_error("writing Anki packages is not supported"))
+    }
+}
+
+fn load_collection(path: &Path, mappings: &[FieldMapping]) -> Result<Vec<Card>, DeckError> {
+    let connection = Connection::open(path).map_err(import_error)?;
+
+    let models: String = connection
+        .query_row("SELECT models FROM col", [], |row| row.get(0))
+        .map_err(import_error)?;
+    let note_types: HashMap<String, NoteType> =
+        serde_json::from_str(&models).map_err(import_error)?;
+
+    let mut statement = connection
+        .prepare("SELECT guid, mid, tags, flds FROM notes ORDER BY id")
+        .map_err(import_error)?;
+    let mut rows = statement.query([]).map_err(import_error)?;
+
+    let mut cards = Vec::new();
+    while let Some(row) = rows.next().map_err(import_error)? {
+        let guid: String = row.get(0).map_err(import_error)?;
+        let note_type_id: i64 = row.get(1).map_err(import_error)?;
+        let tags: String = row.get(2).map_err(import_error)?;
+        let fields: String = row.get(3).map_err(import_error)?;
+        let fields: Vec<&str> = fields.split('\x1f').collect();
+
+        let (front, back) = match note_types.get(&note_type_id.to_string()) {
+            Some(note_type) => field_positions(note_type, mappings)?,
+            None => (0, 1),
+        };
+        let field = |position: usize| strip_html(fields.get(position).copied().unwrap_or(""));
+
+        let mut card = Card::new(field(front), field(back));
+        card.id = Some(guid);
+        card.tags = tags.split_whitespace().map(str::to_string).collect();
+        cards.push(card);
+    }
+
+    if cards.is_empty() {
+        return Err(DeckError::EmptyDeck { path: None });
+    }
+
+    Ok(cards)
+}
+
+/// Positions of the front and back fields within a note of this type.
+fn field_positions(
+    note_type: &NoteType,
+    mappings: &[FieldMapping],
+) -> Result<(usize, usize), DeckError> {
+    let Some(mapping) = mappings
+        .iter()
+        .find(|mapping| mapping.note_type == note_type.name)
+    else {
+        return Ok((0, 1));
+    };
+
+    let position = |name: &str| {
+        note_type
+            .flds
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.ord)
+            .ok_or_else(|| {
+                import_error(format!(
+                    "note type \"{}\" has no field named \"{name}\"",
+                    note_type.name
+                ))
+            })
+    };
+
+    Ok((position(&mapping.front)?, position(&mapping.back)?))
+}
+
+/// Turns an Anki field into plain text: line breaking tags become newlines,
+/// all other tags are dropped and the common entities are decoded.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        if matches!(tag.as_str(), "br" | "div" | "p" | "li")
+            && !text.ends_with('\n')
+            && !text.is_empty()
+        {
+            text.push('\n');
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    decode_entities(&text).trim().to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+
+    /// Builds a minimal `.apkg` with one "Basic" and one "Vocab" note type.
+    fn apkg(notes: &[(&str, i64, &str, &str)]) -> Vec<u8> {
+        let db_path = std::env::temp_dir().join(format!(
+            "flashcards-{}-test-{}.anki2",
+            std::process::id(),
+            notes.len()
+        ));
+        let _ = std::fs::remove_file(&db_path);
+
+        let connection = Connection::open(&db_path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE col (models TEXT);
+                 CREATE TABLE notes (id INTEGER, guid TEXT, mid INTEGER, tags TEXT, flds TEXT);",
+            )
+            .unwrap();
+        let models = r#"{
+            "1": {"name": "Basic", "flds": [{"name": "Front", "ord": 0}, {"name": "Back", "ord": 1}]},
+            "2": {"name": "Vocab", "flds": [
+                {"name": "Word", "ord": 0}, {"name": "Audio", "ord": 1}, {"name": "Meaning", "ord": 2}
+            ]}
+        }"#;
+        connection
+            .execute("INSERT INTO col VALUES (?1)", [models])
+            .unwrap();
+        for (id, (guid, mid, tags, flds)) in notes.iter().enumerate() {
+            connection
+                .execute(
+                    "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![id as i64, guid, mid, tags, flds],
+                )
+                .unwrap();
+        }
+        drop(connection);
+
+        let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        archive
+            .start_file("collection.anki2", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        archive
+            .write_all(&std::fs::read(&db_path).unwrap())
+            .unwrap();
+        std::fs::remove_file(&db_path).unwrap();
+
+        archive.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_load_from_apkg() {
+        let data = apkg(&[
+            (
+                "guid1",
+                1,
+                " rust traits ",
+                "What is <b>Ord</b>?\x1fA total order",
+            ),
+            ("guid2", 2, "", "perro\x1f[sound:perro.ogg]\x1fdog"),
+        ]);
+
+        let cards = load_from_apkg(Cursor::new(&data), &[]).unwrap();
+
+        assert_eq!(cards[0].front(), "What is Ord?");
+        assert_eq!(cards[0].back(), "A total order");
+        assert_eq!(cards[0].tags, vec!["rust", "traits"]);
+        assert_eq!(cards[0].id(), "guid1");
+        assert_eq!(cards[1].front(), "perro");
+        assert_eq!(cards[1].back(), "[sound:perro.ogg]");
+
+        let mappings = vec![parse_mapping("Vocab=Word,Meaning").unwrap()];
+        let cards = load_from_apkg(Cursor::new(&data), &mappings).unwrap();
+
+        assert_eq!(cards[0].back(), "A total order");
+        assert_eq!(cards[1].front(), "perro");
+        assert_eq!(cards[1].back(), "dog");
+    }
+
+    #[test]
+    fn test_load_from_apkg_errors() {
+        let data = apkg(&[("guid1", 2, "", "perro\x1f\x1fdog")]);
+        let mappings = vec![parse_mapping("Vocab=Word,Translation").unwrap()];
+
+        let err = load_from_apkg(Cursor::new(&data), &mappings).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "<deck>: could not import deck: note type \"Vocab\" has no field named \"Translation\""
+        );
+
+        let err = load_from_apkg(Cursor::new(b"not a zip"), &[])
+            .err()
+            .unwrap();
+        assert!(matches!(err, DeckError::Import { .. }));
+    }
+
+    #[test]
+    fn test_parse_mapping() {
+        let cases = vec![
+            (
+                "Basic=Front,Back",
+                Some(FieldMapping {
+                    note_type: "Basic".to_string(),
+                    front: "Front".to_string(),
+                    back: "Back".to_string(),
+                }),
+            ),
+            (
+                "Basic (and reversed card) = Front , Back",
+                Some(FieldMapping {
+                    note_type: "Basic (and reversed card)".to_string(),
+                    front: "Front".to_string(),
+                    back: "Back".to_string(),
+                }),
+            ),
+            ("Basic", None),
+            ("Basic=Front", None),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(parse_mapping(input), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_strip_html() {
+        let cases = vec![
+            ("plain", "plain"),
+            ("<b>bold</b> text", "bold text"),
+            ("one<br>two<br />three", "one\ntwo\nthree"),
+            ("<div>one</div><div>two</div>", "one\ntwo"),
+            ("a &lt; b &amp;&amp; c&nbsp;&gt; d", "a < b && c > d"),
+            ("&#39;quoted&#x27;", "'quoted'"),
+            ("fish &chips; & more", "fish &chips; & more"),
+            ("unclosed <b", "unclosed <b"),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(strip_html(input), expected, "Case {index} failed");
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::Card;
+
+/// Number of options shown for every card, the correct one included.
+pub const OPTION_COUNT: usize = 4;
+
+/// How many of the closest candidates the distractors are drawn from, so the
+/// same three do not show up every time.
+const CANDIDATE_POOL: usize = 6;
+
+/// Picks the options for `cards[answer]`: its own back plus distractors taken
+/// from the other cards, returned as card indexes in random order.
+///
+/// Distractors prefer cards sharing a tag with the answer, then backs of a
+/// similar length, which makes them harder to rule out at a glance. Cards
+/// with the same back as another option are skipped so the same text is
+/// never offered twice, and so are cards facing the other way, whose backs
+/// are fronts.
+pub fn options(cards: &[Card], answer: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let expected = cards[answer].back();
+    let tags = &cards[answer].tags;
+
+    let mut candidates: Vec<usize> = Vec::new();
+    for (index, card) in cards.iter().enumerate() {
+        let duplicate = candidates
+            .iter()
+            .any(|other| cards[*other].back() == card.back());
+
+        if card.back() != expected && !duplicate && card.reversed == cards[answer].reversed {
+            candidates.push(index);
+        }
+    }
+
+    candidates.shuffle(rng);
+    candidates.sort_by_key(|index| {
+        let card = &cards[*index];
+        let shares_tag = card.tags.iter().any(|tag| tags.contains(tag));
+
+        (!shares_tag, card.back().len().abs_diff(expected.len()))
+    });
+    candidates.truncate(CANDIDATE_POOL);
+
+    let mut options: Vec<usize> = candidates
+        .choose_multiple(rng, OPTION_COUNT - 1)
+        .copied()
+        .collect();
+    options.push(answer);
+    options.shuffle(rng);
+
+    options
+}
+
+/// Turns a typed option number (starting at 1) into a card index.
+pub fn pick(options: &[usize], input: &str) -> Option<usize> {
+    let number: usize = input.parse().ok()?;
+
+    options.get(number.checked_sub(1)?).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn deck(backs: &[&str]) -> Vec<Card> {
+        backs
+            .iter()
+            .enumerate()
+            .map(|(index, back)| Card::new(format!("front_{index}"), back.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_options() {
+        let cards = deck(&["one", "two", "three", "four", "five", "six"]);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for answer in 0..cards.len() {
+            let mut options = options(&cards, answer, &mut rng);
+
+            assert_eq!(options.len(), OPTION_COUNT);
+            assert!(options.contains(&answer));
+
+            options.sort();
+            options.dedup();
+            assert_eq!(options.len(), OPTION_COUNT);
+        }
+    }
+
+    #[test]
+    fn test_options_prefer_similar_length() {
+        let cards = deck(&[
+            "abc",
+            "abd",
+            "abe",
+            "abf",
+            "abg",
+            "abh",
+            "abi",
+            "a much longer back that should not be picked",
+        ]);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..20 {
+            assert!(!options(&cards, 0, &mut rng).contains(&7));
+        }
+    }
+
+    #[test]
+    fn test_options_prefer_shared_tags() {
+        let mut cards = deck(&[
+            "abc",
+            "abd",
+            "abe",
+            "abf",
+            "abg",
+            "abh",
+            "abi",
+            "a longer back",
+        ]);
+        cards[0].tags = vec!["verbs".to_string()];
+        cards[7].tags = vec!["verbs".to_string()];
+        let mut rng = StdRng::seed_from_u64(7);
+
+        // Without the shared tag the long back would never be offered
+        let offered = (0..20).any(|_| options(&cards, 0, &mut rng).contains(&7));
+        assert!(offered);
+    }
+
+    #[test]
+    fn test_options_skip_duplicate_backs() {
+        let cards = deck(&["same", "same", "other", "same"]);
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let mut options = options(&cards, 0, &mut rng);
+        options.sort();
+
+        assert_eq!(options, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_options_skip_reversed_cards() {
+        let mut cards = deck(&["one", "two", "three", "four", "five", "six"]);
+        cards[4].reversed = true;
+        cards[5].reversed = true;
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let mut options = options(&cards, 0, &mut rng);
+        options.sort();
+
+        assert_eq!(options, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_pick() {
+        let options = vec![5, 2, 9, 0];
+        let cases = vec![
+            ("1", Some(5)),
+            ("4", Some(0)),
+            ("0", None),
+            ("5", None),
+            ("x", None),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(pick(&options, input), expected, "Case {index} failed");
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::anki::{self, FieldMapping};
+use crate::direction::{parse_direction, Direction};
+use crate::export::{parse_export, Export};
+use crate::format::Format;
+use crate::media::{parse_graphics, Graphics};
+use crate::order::{parse_order, Order};
+use crate::{parse_mode, Mode};
+
+/// Study flashcard decks from the terminal.
+///
+/// Defaults for the session options can be set in
+/// `~/.config/flashcards/config.toml`; flags always win over the file.
+#[derive(Debug, Parser)]
+#[command(name = "flashcards", version)]
+pub struct Cli {
+    /// Read defaults from this file instead of the usual config file.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: CliCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Review the cards that are due, flipping and grading each one.
+    Study(SessionArgs),
+    /// Review the due cards by typing the answers.
+    Quiz(SessionArgs),
+    /// Study the due cards in a browser, on a local web server.
+    Serve(ServeArgs),
+    /// Show review statistics for decks.
+    Stats(StatsArgs),
+    /// Add the cards of another deck, such as an Anki package, to a deck.
+    Import(ImportArgs),
+    /// Write the cards of decks to a single file.
+    Export(ExportArgs),
+    /// Check decks for errors without studying them.
+    Validate(DeckArgs),
+    /// Create a deck with an example card.
+    New(NewArgs),
+}
+
+/// Which decks to use, and how to read them.
+#[derive(Debug, Args)]
+pub struct DeckArgs {
+    /// Deck files, or directories to search for decks.
+    #[arg(required = true, value_name = "DECK")]
+    pub paths: Vec<PathBuf>,
+
+    /// Read decks in this format instead of guessing from the extension:
+    /// csv, tsv, json, toml, md or apkg.
+    #[arg(long, value_parser = format_arg)]
+    pub format: Option<Format>,
+
+    /// Only use decks whose name or path matches; `*` and `?` are wildcards.
+    #[arg(long = "deck", value_name = "PATTERN")]
+    pub decks: Vec<String>,
+
+    /// Only use cards with a matching tag; `*` and `?` are wildcards.
+    #[arg(long = "tag", value_name = "PATTERN")]
+    pub tags: Vec<String>,
+
+    /// Fields to use for an Anki note type, as "Note type=Front field,Back
+    /// field".
+    #[arg(long = "anki-fields", value_name = "MAPPING", value_parser = mapping_arg)]
+    pub mappings: Vec<FieldMapping>,
+}
+
+#[derive(Debug, Args)]
+#[command(group(ArgGroup::new("scripted").args(["script", "batch"])))]
+pub struct SessionArgs {
+    #[command(flatten)]
+    pub decks: DeckArgs,
+
+    /// How to answer the cards: study, quiz or choice.
+    #[arg(long, value_parser = mode_arg)]
+    pub mode: Option<Mode>,
+
+    /// Which side of the cards to show first: front-to-back, back-to-front,
+    /// random, or both as separate cards.
+    #[arg(long, value_parser = direction_arg)]
+    pub direction: Option<Direction>,
+
+    /// The order to go through the cards in: file, shuffle, alphabetical,
+    /// weakest, oldest, slowest or interleaved.
+    #[arg(long, value_parser = order_arg)]
+    pub order: Option<Order>,
+
+    /// Shuffle the due cards before starting, the same as `--order shuffle`.
+    #[arg(long, conflicts_with = "order")]
+    pub shuffle: bool,
+
+    /// Schedule the cards with Leitner boxes instead of SM-2.
+    #[arg(long)]
+    pub leitner: bool,
+
+    /// Days between reviews for each Leitner box, e.g. 1,2,4,8,16. The
+    /// number of entries is the number of boxes.
+    #[arg(
+        long,
+        value_name = "DAYS",
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub cadence: Option<Vec<u32>>,
+
+    /// Give each card a time limit, 10 seconds unless another is given.
+    /// Running out of time counts as a miss.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub timed: Option<u64>,
+
+    /// Seed for shuffling, so a session can be repeated.
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Review at most this many cards.
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub limit: Option<usize>,
+
+    /// Typos forgiven when checking typed answers.
+    #[arg(long)]
+    pub threshold: Option<usize>,
+
+    /// Use the line based interface even on a terminal.
+    #[arg(long)]
+    pub line: bool,
+
+    /// Show card text as it is written, without rendering its Markdown.
+    #[arg(long)]
+    pub plain: bool,
+
+    /// How to show images: kitty, sixel or text. By default this is
+    /// guessed from the terminal.
+    #[arg(long, value_parser = images_arg)]
+    pub images: Option<Graphics>,
+
+    /// Command to play sounds with when a card is flipped, such as
+    /// "mpv --no-video". The files go in place of a `{}`, or at the end.
+    #[arg(long, value_name = "COMMAND")]
+    pub audio_command: Option<String>,
+
+    /// Run the commands in this file, one per line, instead of reading them
+    /// from the keyboard. Each screen is printed in turn, without clearing.
+    #[arg(long, value_name = "PATH", conflicts_with = "batch")]
+    pub script: Option<PathBuf>,
+
+    /// Run the commands piped in on stdin, the same as `--script`.
+    #[arg(long)]
+    pub batch: bool,
+
+    /// Print the cards and what each command did as lines of JSON, for
+    /// `--script` and `--batch`.
+    #[arg(long, requires = "scripted")]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub session: SessionArgs,
+
+    /// Address to listen on. Only this machine can connect to the default.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// Port to listen on, or 0 to pick a free one.
+    #[arg(long, default_value_t = 8080)]
+    pub port: u16,
+}
+
+#[derive(Debug, Args)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub decks: DeckArgs,
+
+    /// Print the report as JSON instead of tables.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportArgs {
+    /// The deck to import cards from.
+    pub source: PathBuf,
+
+    /// The deck to add the cards to. It is created if it does not exist.
+    pub deck: PathBuf,
+
+    /// Read the source in this format instead of guessing from the extension.
+    #[arg(long, value_parser = format_arg)]
+    pub format: Option<Format>,
+
+    /// Fields to use for an Anki note type, as "Note type=Front field,Back
+    /// field".
+    #[arg(long = "anki-fields", value_name = "MAPPING", value_parser = mapping_arg)]
+    pub mappings: Vec<FieldMapping>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub decks: DeckArgs,
+
+    /// The file to write. Without `--to`, its extension picks the deck
+    /// format to write; with it, the export goes to stdout if this is left
+    /// out.
+    #[arg(long, short, required_unless_present = "to")]
+    pub output: Option<PathBuf>,
+
+    /// Export for use elsewhere instead of as a deck: csv with tags and
+    /// scheduling, anki for Anki's text import, or html to print.
+    #[arg(long, value_parser = export_arg)]
+    pub to: Option<Export>,
+}
+
+#[derive(Debug, Args)]
+pub struct NewArgs {
+    /// The deck file to create; its extension picks the format.
+    pub path: PathBuf,
+}
+
+fn format_arg(value: &str) -> Result<Format, String> {
+    Format::parse(value).ok_or_else(|| "expected csv, tsv, json, toml, md or apkg".to_string())
+}
+
+fn mapping_arg(value: &str) -> Result<FieldMapping, String> {
+    anki::parse_mapping(value)
+        .ok_or_else(|| "expected \"Note type=Front field,Back field\"".to_string())
+}
+
+fn images_arg(value: &str) -> Result<Graphics, String> {
+    parse_graphics(value).ok_or_else(|| "expected kitty, sixel or text".to_string())
+}
+
+fn export_arg(value: &str) -> Result<Export, String> {
+    parse_export(value).ok_or_else(|| "expected csv, anki or html".to_string())
+}
+
+fn mode_arg(value: &str) -> Result<Mode, String> {
+    parse_mode(value).ok_or_else(|| "expected study, quiz or choice".to_string())
+}
+
+fn order_arg(value: &str) -> Result<Order, String> {
+    parse_order(value).ok_or_else(|| {
+        "expected file, shuffle, alphabetical, weakest, oldest, slowest or interleaved".to_string()
+    })
+}
+
+fn direction_arg(value: &str) -> Result<Direction, String> {
+    parse_direction(value)
+        .ok_or_else(|| "expected front-to-back, back-to-front, random or both".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_study() {
+        let cli = Cli::try_parse_from([
+            "flashcards",
+            "study",
+            "rust.csv",
+            "decks",
+            "--tag",
+            "traits",
+            "--mode",
+            "choice",
+            "--seed",
+            "7",
+            "--limit",
+            "20",
+        ])
+        .unwrap();
+
+        let CliCommand::Study(args) = cli.command else {
+            panic!("expected the study command");
+        };
+        assert_eq!(
+            args.decks.paths,
+            vec![PathBuf::from("rust.csv"), PathBuf::from("decks")]
+        );
+        assert_eq!(args.decks.tags, vec!["traits"]);
+        assert_eq!(args.mode, Some(Mode::Choice));
+        assert_eq!(args.seed, Some(7));
+        assert_eq!(args.limit, Some(20));
+        assert_eq!(args.direction, None);
+        assert_eq!(args.order, None);
+        assert!(!args.shuffle);
+        assert_eq!(args.timed, None);
+
+        let cases = vec![
+            (vec!["--timed"], Some(10)),
+            (vec!["--timed=5"], Some(5)),
+            (vec!["--timed", "decks"], Some(10)),
+        ];
+        for (index, (flags, expected)) in cases.into_iter().enumerate() {
+            let cli =
+                Cli::try_parse_from(["flashcards", "study", "rust.csv"].into_iter().chain(flags))
+                    .unwrap();
+            let CliCommand::Study(args) = cli.command else {
+                panic!("expected the study command");
+            };
+            assert_eq!(args.timed, expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_parse_serve() {
+        let cases = vec![
+            (vec![], "127.0.0.1", 8080),
+            (vec!["--port", "0", "--host", "0.0.0.0"], "0.0.0.0", 0),
+        ];
+        for (index, (flags, host, port)) in cases.into_iter().enumerate() {
+            let cli =
+                Cli::try_parse_from(["flashcards", "serve", "rust.csv"].into_iter().chain(flags))
+                    .unwrap();
+            let CliCommand::Serve(args) = cli.command else {
+                panic!("expected the serve command");
+            };
+            assert_eq!(args.host, host, "Case {index} failed");
+            assert_eq!(args.port, port, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = vec![
+            vec!["flashcards"],
+            vec!["flashcards", "study"],
+            vec!["flashcards", "study", "rust.csv", "--mode", "exam"],
+            vec!["flashcards", "study", "rust.csv", "--format", "xls"],
+            vec!["flashcards", "export", "rust.csv"],
+            vec!["flashcards", "export", "rust.csv", "--to", "pdf"],
+            vec!["flashcards", "study", "rust.csv", "--cadence", "1,0,4"],
+            vec!["flashcards", "study", "rust.csv", "--timed=0"],
+            vec!["flashcards", "study", "rust.csv", "--limit", "0"],
+            vec!["flashcards", "study", "rust.csv", "--json"],
+            vec![
+                "flashcards",
+                "study",
+                "rust.csv",
+                "--batch",
+                "--script",
+                "a.txt",
+            ],
+            vec![
+                "flashcards",
+                "study",
+                "rust.csv",
+                "--shuffle",
+                "--order",
+                "file",
+            ],
+        ];
+
+        for (index, args) in cases.into_iter().enumerate() {
+            assert!(Cli::try_parse_from(args).is_err(), "Case {index} failed");
+        }
+    }
+}
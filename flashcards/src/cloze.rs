@@ -0,0 +1,214 @@
+use crate::Card;
+
+/// A piece of a cloze front: plain text, or a `{{c1::text::hint}}` deletion.
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Deletion {
+        group: u32,
+        text: &'a str,
+        hint: Option<&'a str>,
+    },
+}
+
+/// Splits `input` into text and deletions. Anything that only looks like
+/// the start of a deletion, such as `{{c1:` or an unclosed `{{c1::`, is
+/// left as text.
+fn segments(input: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = input;
+    let mut text_start = 0;
+
+    while let Some(start) = rest.find("{{c") {
+        let after = &rest[start + 3..];
+        let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let deletion = after[..digits].parse().ok().and_then(|group| {
+            let body = after[digits..].strip_prefix("::")?;
+            let end = body.find("}}")?;
+            let (text, hint) = match body[..end].split_once("::") {
+                Some((text, hint)) => (text, Some(hint)),
+                None => (&body[..end], None),
+            };
+            let length = 3 + digits + 2 + end + 2;
+            Some((Segment::Deletion { group, text, hint }, length))
+        });
+
+        let offset = input.len() - rest.len();
+        match deletion {
+            Some((deletion, length)) => {
+                if text_start < offset + start {
+                    segments.push(Segment::Text(&input[text_start..offset + start]));
+                }
+                segments.push(deletion);
+                rest = &rest[start + length..];
+                text_start = input.len() - rest.len();
+            }
+            None => rest = &rest[start + 3..],
+        }
+    }
+
+    if text_start < input.len() {
+        segments.push(Segment::Text(&input[text_start..]));
+    }
+
+    segments
+}
+
+/// Whether `text` has any cloze deletions.
+pub fn has_deletions(text: &str) -> bool {
+    segments(text)
+        .iter()
+        .any(|segment| matches!(segment, Segment::Deletion { .. }))
+}
+
+/// Turns a card whose front has cloze deletions into one card per group.
+///
+/// Each card hides its group's deletions behind `[...]`, or `[hint]` when
+/// the deletion has one, and shows the text of the others. The back is the
+/// whole sentence, followed by the row's own back if it has one. Cards get
+/// the group added to their id, so each keeps its own progress. Cards
+/// without deletions are returned as they are.
+pub fn expand(card: Card) -> Vec<Card> {
+    let segments = segments(&card.front);
+    let mut groups: Vec<u32> = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Deletion { group, .. } => Some(*group),
+            Segment::Text(_) => None,
+        })
+        .collect();
+    if groups.is_empty() {
+        return vec![card];
+    }
+    groups.sort();
+    groups.dedup();
+
+    let sentence: String = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Text(text) | Segment::Deletion { text, .. } => *text,
+        })
+        .collect();
+    let back = if card.back.is_empty() {
+        sentence
+    } else {
+        format!("{sentence}\n\n{}", card.back)
+    };
+
+    groups
+        .iter()
+        .map(|&hidden| {
+            let front = segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Text(text) => text.to_string(),
+                    Segment::Deletion { group, hint, .. } if *group == hidden => {
+                        format!("[{}]", hint.unwrap_or("..."))
+                    }
+                    Segment::Deletion { text, .. } => text.to_string(),
+                })
+                .collect();
+
+            Card {
+                id: Some(format!("{}:c{hidden}", card.id())),
+                cloze: Some(hidden),
+                front,
+                back: back.clone(),
+                ..card.clone()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        let cases = vec![
+            ("plain", vec![Segment::Text("plain")]),
+            (
+                "a {{c1::b}} c",
+                vec![
+                    Segment::Text("a "),
+                    Segment::Deletion {
+                        group: 1,
+                        text: "b",
+                        hint: None,
+                    },
+                    Segment::Text(" c"),
+                ],
+            ),
+            (
+                "{{c12::Ord::trait name}}",
+                vec![Segment::Deletion {
+                    group: 12,
+                    text: "Ord",
+                    hint: Some("trait name"),
+                }],
+            ),
+            (
+                "{{c::x}} {{c1:x}}",
+                vec![Segment::Text("{{c::x}} {{c1:x}}")],
+            ),
+            ("open {{c1::x", vec![Segment::Text("open {{c1::x")]),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(segments(input), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_has_deletions() {
+        assert!(has_deletions("The {{c1::Ord}} trait"));
+        assert!(!has_deletions("The {{c1:Ord}} trait"));
+        assert!(!has_deletions("plain"));
+    }
+
+    #[test]
+    fn test_expand() {
+        let card = Card::new(
+            "The {{c1::Ord::trait name}} trait requires {{c2::Eq}} and {{c1::PartialOrd}}"
+                .to_string(),
+            "See std::cmp".to_string(),
+        );
+        let id = card.id();
+
+        let cards = expand(card);
+
+        let fronts: Vec<&str> = cards.iter().map(|card| card.front()).collect();
+        assert_eq!(
+            fronts,
+            vec![
+                "The [trait name] trait requires Eq and [...]",
+                "The Ord trait requires [...] and PartialOrd",
+            ]
+        );
+        for (index, card) in cards.iter().enumerate() {
+            assert_eq!(
+                card.back(),
+                "The Ord trait requires Eq and PartialOrd\n\nSee std::cmp",
+                "Case {index} failed"
+            );
+            assert_eq!(card.cloze, Some(index as u32 + 1), "Case {index} failed");
+            assert_eq!(
+                card.id(),
+                format!("{id}:c{}", index + 1),
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_expand_plain_card() {
+        let card = Card::new("front".to_string(), "back".to_string());
+
+        let cards = expand(card);
+
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].front(), "front");
+        assert_eq!(cards[0].id, None);
+    }
+}
@@ -0,0 +1,341 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::cli::SessionArgs;
+use crate::direction::{parse_direction, Direction};
+use crate::error::Error;
+use crate::leitner;
+use crate::media::{self, parse_graphics, Graphics};
+use crate::order::{parse_order, Order};
+use crate::{parse_mode, quiz, Mode};
+
+/// Defaults for session options, read from `config.toml`:
+///
+/// ```toml
+/// mode = "quiz"
+/// direction = "back-to-front"
+/// order = "weakest"
+/// leitner = true
+/// cadence = [1, 3, 7]
+/// timed = 15
+/// limit = 50
+/// audio_command = "mpv --no-video"
+/// ```
+///
+/// Every key is optional and uses the same values as the matching flag.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub mode: Option<String>,
+    pub direction: Option<String>,
+    pub order: Option<String>,
+    pub leitner: Option<bool>,
+    pub cadence: Option<Vec<u32>>,
+    /// Seconds allowed for each card.
+    pub timed: Option<u64>,
+    pub seed: Option<u64>,
+    pub limit: Option<usize>,
+    pub threshold: Option<usize>,
+    pub line: Option<bool>,
+    pub plain: Option<bool>,
+    pub images: Option<String>,
+    /// Command to play sounds with.
+    pub audio_command: Option<String>,
+}
+
+/// The options a session runs with, once flags and config are merged.
+#[derive(Debug, PartialEq)]
+pub struct Settings {
+    pub mode: Mode,
+    pub direction: Direction,
+    pub order: Order,
+    /// Leitner boxes to schedule with instead of SM-2, as days per box.
+    pub leitner: Option<Vec<u32>>,
+    /// Time allowed for answering each card, in timed sessions.
+    pub timed: Option<Duration>,
+    pub seed: Option<u64>,
+    pub limit: Option<usize>,
+    pub threshold: usize,
+    pub line: bool,
+    /// Show card text as written rather than rendering it as Markdown.
+    pub plain: bool,
+    pub images: Graphics,
+    pub audio_command: Option<String>,
+}
+
+/// `$XDG_CONFIG_HOME/flashcards/config.toml`, falling back to
+/// `~/.config/flashcards/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("flashcards").join("config.toml"))
+}
+
+fn config_error(path: &Path, message: impl ToString) -> Error {
+    Error::Config {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    }
+}
+
+impl Config {
+    /// Loads the config file at `path`, or the default one when no path is
+    /// given. Only the default file may be missing.
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => {
+                return Ok(Config::default())
+            }
+            Err(err) => return Err(config_error(&path, err)),
+        };
+
+        Config::parse(&text).map_err(|message| config_error(&path, message))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(text).map_err(|err| err.message().to_string())?;
+
+        if config
+            .mode
+            .as_deref()
+            .is_some_and(|mode| parse_mode(mode).is_none())
+        {
+            return Err("mode must be study, quiz or choice".to_string());
+        }
+        if config
+            .direction
+            .as_deref()
+            .is_some_and(|direction| parse_direction(direction).is_none())
+        {
+            return Err(
+                "direction must be front-to-back, back-to-front, random or both".to_string(),
+            );
+        }
+        if config
+            .order
+            .as_deref()
+            .is_some_and(|order| parse_order(order).is_none())
+        {
+            return Err(
+                "order must be file, shuffle, alphabetical, weakest, oldest, slowest or \
+                 interleaved"
+                    .to_string(),
+            );
+        }
+
+        if config
+            .cadence
+            .as_ref()
+            .is_some_and(|cadence| cadence.is_empty() || cadence.contains(&0))
+        {
+            return Err("cadence must list at least one box, each of 1 day or more".to_string());
+        }
+        if config
+            .images
+            .as_deref()
+            .is_some_and(|images| parse_graphics(images).is_none())
+        {
+            return Err("images must be kitty, sixel or text".to_string());
+        }
+        if config.timed == Some(0) {
+            return Err("timed must be at least 1 second".to_string());
+        }
+        if config.limit == Some(0) {
+            return Err("limit must be at least 1 card".to_string());
+        }
+
+        Ok(config)
+    }
+
+    /// Merges the flags of a session over the config. `mode` is the mode of
+    /// the subcommand, which only gives way to the `--mode` flag.
+    pub fn settings(&self, args: &SessionArgs, mode: Option<Mode>) -> Settings {
+        Settings {
+            mode: args
+                .mode
+                .or(mode)
+                .or(self.mode.as_deref().and_then(parse_mode))
+                .unwrap_or(Mode::Study),
+            direction: args
+                .direction
+                .or(self.direction.as_deref().and_then(parse_direction))
+                .unwrap_or(Direction::FrontToBack),
+            order: args
+                .order
+                .or(args.shuffle.then_some(Order::Shuffle))
+                .or(self.order.as_deref().and_then(parse_order))
+                .unwrap_or(Order::File),
+            leitner: (args.leitner || self.leitner.unwrap_or(false)).then(|| {
+                args.cadence
+                    .clone()
+                    .or_else(|| self.cadence.clone())
+                    .unwrap_or_else(|| leitner::DEFAULT_CADENCE.to_vec())
+            }),
+            timed: args.timed.or(self.timed).map(Duration::from_secs),
+            seed: args.seed.or(self.seed),
+            limit: args.limit.or(self.limit),
+            threshold: args
+                .threshold
+                .or(self.threshold)
+                .unwrap_or(quiz::DEFAULT_THRESHOLD),
+            line: args.line || self.line.unwrap_or(false),
+            plain: args.plain || self.plain.unwrap_or(false),
+            images: args
+                .images
+                .or(self.images.as_deref().and_then(parse_graphics))
+                .unwrap_or_else(media::detect),
+            audio_command: args
+                .audio_command
+                .clone()
+                .or_else(|| self.audio_command.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, CliCommand};
+    use clap::Parser;
+
+    fn session_args(args: &[&str]) -> SessionArgs {
+        let cli =
+            Cli::try_parse_from(["flashcards", "study", "deck.csv"].iter().chain(args)).unwrap();
+
+        match cli.command {
+            CliCommand::Study(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse("mode = \"quiz\"\nlimit = 20\nline = true\n").unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                mode: Some("quiz".to_string()),
+                limit: Some(20),
+                line: Some(true),
+                ..Config::default()
+            }
+        );
+
+        let cases = vec![
+            ("mode = \"exam\"", "mode must be study, quiz or choice"),
+            (
+                "limit = \"ten\"",
+                "invalid type: string \"ten\", expected usize",
+            ),
+            ("colour = true", "unknown field `colour`"),
+            ("order = \"random\"", "order must be file, shuffle"),
+            ("cadence = []", "cadence must list at least one box"),
+            ("cadence = [1, 0]", "cadence must list at least one box"),
+            ("timed = 0", "timed must be at least 1 second"),
+            ("limit = 0", "limit must be at least 1 card"),
+            ("images = \"ascii\"", "images must be kitty, sixel or text"),
+        ];
+
+        for (index, (text, expected)) in cases.into_iter().enumerate() {
+            let err = Config::parse(text).err().unwrap();
+            assert!(err.starts_with(expected), "Case {index} failed: {err}");
+        }
+    }
+
+    #[test]
+    fn test_settings() {
+        let config = Config {
+            mode: Some("choice".to_string()),
+            direction: Some("back-to-front".to_string()),
+            order: Some("weakest".to_string()),
+            limit: Some(20),
+            threshold: Some(2),
+            images: Some("text".to_string()),
+            ..Config::default()
+        };
+
+        let settings = config.settings(&session_args(&["--limit", "5", "--shuffle"]), None);
+        assert_eq!(
+            settings,
+            Settings {
+                mode: Mode::Choice,
+                direction: Direction::BackToFront,
+                order: Order::Shuffle,
+                leitner: None,
+                timed: None,
+                seed: None,
+                limit: Some(5),
+                threshold: 2,
+                line: false,
+                plain: false,
+                images: Graphics::Text,
+                audio_command: None,
+            }
+        );
+        let settings = config.settings(&session_args(&["--images", "kitty"]), None);
+        assert_eq!(settings.images, Graphics::Kitty);
+
+        // The subcommand's mode wins over the config, the flag over both
+        let settings = config.settings(&session_args(&[]), Some(Mode::Quiz));
+        assert_eq!(settings.mode, Mode::Quiz);
+        let settings = config.settings(&session_args(&["--mode", "study"]), Some(Mode::Quiz));
+        assert_eq!(settings.mode, Mode::Study);
+
+        let settings = Config::default().settings(&session_args(&[]), None);
+        assert_eq!(settings.mode, Mode::Study);
+        assert_eq!(settings.direction, Direction::FrontToBack);
+        assert_eq!(settings.threshold, quiz::DEFAULT_THRESHOLD);
+        assert_eq!(settings.order, Order::File);
+
+        let settings = config.settings(&session_args(&["--order", "oldest"]), None);
+        assert_eq!(settings.order, Order::Oldest);
+        let settings = config.settings(&session_args(&[]), None);
+        assert_eq!(settings.order, Order::Weakest);
+
+        let settings = config.settings(&session_args(&["--leitner"]), None);
+        assert_eq!(settings.leitner, Some(leitner::DEFAULT_CADENCE.to_vec()));
+        let config = Config {
+            leitner: Some(true),
+            cadence: Some(vec![1, 3]),
+            ..Config::default()
+        };
+        let settings = config.settings(&session_args(&[]), None);
+        assert_eq!(settings.leitner, Some(vec![1, 3]));
+        let settings = config.settings(&session_args(&["--cadence", "2,4,8"]), None);
+        assert_eq!(settings.leitner, Some(vec![2, 4, 8]));
+
+        let config = Config {
+            timed: Some(15),
+            ..Config::default()
+        };
+        let settings = config.settings(&session_args(&[]), None);
+        assert_eq!(settings.timed, Some(Duration::from_secs(15)));
+        let settings = config.settings(&session_args(&["--timed"]), None);
+        assert_eq!(settings.timed, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_load_missing_config() {
+        let path = Path::new("does/not/exist.toml");
+
+        let err = Config::load(Some(path)).err().unwrap();
+
+        assert!(err.to_string().starts_with("does/not/exist.toml: "));
+    }
+}
@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::DeckError;
+use crate::format::{self, Format};
+use crate::progress::ProgressStore;
+use crate::Card;
+
+/// One of the deck files studied in a session, with its own progress.
+pub struct Deck {
+    /// Short name shown in the UI and matched by `--deck`.
+    pub name: String,
+    pub path: PathBuf,
+    /// The format the deck was read in, when it was not picked from the
+    /// extension. Edits are written back in the same format.
+    pub format: Option<Format>,
+    /// The cards as read from the file, before cloze rows are expanded or
+    /// cards are turned around. Rows deleted in a session are `None`.
+    pub rows: Vec<Option<Card>>,
+    pub store: ProgressStore,
+}
+
+impl Deck {
+    pub fn new(path: PathBuf) -> std::io::Result<Self> {
+        let store = ProgressStore::load(ProgressStore::sidecar_path(&path))?;
+
+        Ok(Self {
+            name: deck_name(&path),
+            path,
+            format: None,
+            rows: Vec::new(),
+            store,
+        })
+    }
+
+    /// Whether the deck file can be edited in a session.
+    pub fn editable(&self) -> bool {
+        self.format
+            .unwrap_or_else(|| Format::from_path(&self.path))
+            .round_trips()
+    }
+
+    /// Writes the rows back to the deck file, leaving out deleted ones.
+    pub fn write(&self) -> Result<(), DeckError> {
+        let cards: Vec<Card> = self.rows.iter().flatten().cloned().collect();
+
+        format::write_deck(&self.path, self.format, &cards)
+    }
+}
+
+/// The file name without its extension, e.g. `rust` for `decks/rust.csv`.
+pub fn deck_name(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Expands the paths given on the command line into deck files. Directories
+/// are searched recursively for files with a known deck extension, in name
+/// order; files named directly are always kept, whatever their extension.
+pub fn deck_paths(paths: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut decks = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            collect_decks(path, &mut decks)?;
+        } else {
+            decks.push(path.clone());
+        }
+    }
+
+    Ok(decks)
+}
+
+fn collect_decks(dir: &Path, decks: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            collect_decks(&path, decks)?;
+        } else if is_deck_file(&path) {
+            decks.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_deck_file(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+
+    // Progress sidecars are JSON too, but never decks
+    !name.ends_with(".progress.json")
+        && path
+            .extension()
+            .is_some_and(|extension| Format::parse(&extension.to_string_lossy()).is_some())
+}
+
+/// Narrows a session down to some decks or tags. Each list is a set of
+/// alternatives and may use `*` and `?` wildcards; an empty list lets
+/// everything through.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub decks: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+impl Filter {
+    /// Decks are matched by name or by path.
+    pub fn matches(&self, card: &Card, deck: &Deck) -> bool {
+        let deck_path = deck.path.to_string_lossy();
+        let deck_matches = self.decks.is_empty()
+            || self
+                .decks
+                .iter()
+                .any(|pattern| glob_match(pattern, &deck.name) || glob_match(pattern, &deck_path));
+
+        let tag_matches = self.tags.is_empty()
+            || self
+                .tags
+                .iter()
+                .any(|pattern| card.tags.iter().any(|tag| glob_match(pattern, tag)));
+
+        deck_matches && tag_matches
+    }
+}
+
+/// Matches `text` against a shell-style pattern where `*` stands for any
+/// run of characters and `?` for exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and how much of the text it has swallowed
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character and retry
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let cases = vec![
+            ("rust", "rust", true),
+            ("rust", "rusty", false),
+            ("rust*", "rust-traits", true),
+            ("*traits", "rust-traits", true),
+            ("r?st", "rust", true),
+            ("r?st", "rst", false),
+            ("*a*b*", "xaybz", true),
+            ("*a*b", "xaybz", false),
+            ("decks/*.csv", "decks/rust.csv", true),
+            ("*", "", true),
+            ("", "", true),
+        ];
+
+        for (index, (pattern, text, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(glob_match(pattern, text), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let deck = Deck {
+            name: "rust".to_string(),
+            path: PathBuf::from("decks/rust.csv"),
+            format: None,
+            rows: Vec::new(),
+            store: ProgressStore::load(PathBuf::from("does/not/exist.json")).unwrap(),
+        };
+        let mut card = Card::new("front".to_string(), "back".to_string());
+        card.tags = vec!["traits".to_string(), "generics".to_string()];
+
+        let filter = |decks: &[&str], tags: &[&str]| Filter {
+            decks: decks.iter().map(|deck| deck.to_string()).collect(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        };
+        let cases = vec![
+            (filter(&[], &[]), true),
+            (filter(&["rust"], &[]), true),
+            (filter(&["go"], &[]), false),
+            (filter(&["go", "ru*"], &[]), true),
+            (filter(&["decks/*.csv"], &[]), true),
+            (filter(&[], &["traits"]), true),
+            (filter(&[], &["gen*"]), true),
+            (filter(&[], &["lifetimes"]), false),
+            (filter(&["rust"], &["lifetimes"]), false),
+        ];
+
+        for (index, (filter, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                filter.matches(&card, &deck),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_deck_paths() {
+        let dir = std::env::temp_dir().join(format!("flashcards-{}-decks", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in [
+            "b.csv",
+            "a.md",
+            "a.md.progress.json",
+            "notes.txt",
+            "nested/c.json",
+        ] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let paths = deck_paths(&[dir.clone(), PathBuf::from("other.txt")]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("a.md"),
+                dir.join("b.csv"),
+                dir.join("nested/c.json"),
+                PathBuf::from("other.txt"),
+            ]
+        );
+        assert_eq!(deck_name(&paths[2]), "c");
+    }
+}
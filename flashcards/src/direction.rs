@@ -0,0 +1,155 @@
+use rand::Rng;
+
+use crate::Card;
+
+/// Which side of the cards is shown first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    FrontToBack,
+    BackToFront,
+    /// Each card is turned one way or the other at random.
+    Random,
+    /// Each card is studied both ways, as two separate items.
+    Both,
+}
+
+pub fn parse_direction(input: &str) -> Option<Direction> {
+    match input {
+        "front-to-back" => Some(Direction::FrontToBack),
+        "back-to-front" => Some(Direction::BackToFront),
+        "random" => Some(Direction::Random),
+        "both" => Some(Direction::Both),
+        _ => None,
+    }
+}
+
+/// The card turned around. It gets an id of its own so that its progress
+/// is kept apart from the progress of the card facing the usual way.
+pub fn reversed(card: Card) -> Card {
+    Card {
+        id: Some(format!("{}:reversed", card.id())),
+        front: card.back,
+        back: card.front,
+        reversed: !card.reversed,
+        ..card
+    }
+}
+
+/// Turns the cards to face the given direction.
+///
+/// Cards with the `reverse` column set are studied both ways whatever the
+/// direction. The turned-around copies go after all the other cards, so a
+/// card and its reverse are not seen back to back.
+pub fn arrange(cards: Vec<Card>, direction: Direction, rng: &mut impl Rng) -> Vec<Card> {
+    let mut arranged = Vec::with_capacity(cards.len());
+    let mut copies = Vec::new();
+
+    for card in cards {
+        let both = direction == Direction::Both || card.reverse;
+        let turn = match direction {
+            Direction::FrontToBack | Direction::Both => false,
+            Direction::BackToFront => true,
+            Direction::Random => rng.gen_bool(0.5),
+        };
+
+        if both {
+            copies.push(reversed(card.clone()));
+            arranged.push(card);
+        } else if turn {
+            arranged.push(reversed(card));
+        } else {
+            arranged.push(card);
+        }
+    }
+
+    arranged.extend(copies);
+    arranged
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn deck() -> Vec<Card> {
+        let mut cards: Vec<Card> = ["one", "two", "three"]
+            .iter()
+            .map(|front| Card::new(front.to_string(), format!("{front} back")))
+            .collect();
+        cards[2].reverse = true;
+        cards
+    }
+
+    fn fronts(cards: &[Card]) -> Vec<&str> {
+        cards.iter().map(|card| card.front()).collect()
+    }
+
+    #[test]
+    fn test_parse_direction() {
+        let cases = vec![
+            ("front-to-back", Some(Direction::FrontToBack)),
+            ("back-to-front", Some(Direction::BackToFront)),
+            ("random", Some(Direction::Random)),
+            ("both", Some(Direction::Both)),
+            ("sideways", None),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(parse_direction(input), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_reversed() {
+        let card = Card::new("front".to_string(), "back".to_string());
+        let id = card.id();
+
+        let card = reversed(card);
+
+        assert_eq!(card.front(), "back");
+        assert_eq!(card.back(), "front");
+        assert!(card.reversed);
+        assert_eq!(card.id(), format!("{id}:reversed"));
+    }
+
+    #[test]
+    fn test_arrange() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let cases = vec![
+            (
+                Direction::FrontToBack,
+                vec!["one", "two", "three", "three back"],
+            ),
+            (
+                Direction::BackToFront,
+                vec!["one back", "two back", "three", "three back"],
+            ),
+            (
+                Direction::Both,
+                vec!["one", "two", "three", "one back", "two back", "three back"],
+            ),
+        ];
+
+        for (index, (direction, expected)) in cases.into_iter().enumerate() {
+            let cards = arrange(deck(), direction, &mut rng);
+            assert_eq!(fronts(&cards), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_arrange_random() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut turned = 0;
+
+        for _ in 0..20 {
+            let cards = arrange(deck(), Direction::Random, &mut rng);
+            assert_eq!(cards.len(), 4);
+            turned += cards.iter().filter(|card| card.reversed).count();
+        }
+
+        // Only the `reverse` card's copy is always turned
+        assert!(turned > 20 && turned < 60);
+    }
+}
@@ -0,0 +1,327 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Exit codes from `sysexits.h`, so scripts can tell failures apart.
+pub const EXIT_USAGE: u8 = 64;
+pub const EXIT_DATA: u8 = 65;
+pub const EXIT_NO_INPUT: u8 = 66;
+pub const EXIT_IO: u8 = 74;
+pub const EXIT_CONFIG: u8 = 78;
+
+/// Everything that can go wrong while loading a deck.
+///
+/// Errors are created without a path by the loaders, which only see a reader,
+/// and get one attached with [`DeckError::with_path`] by whoever opened the
+/// file.
+#[derive(Debug)]
+pub enum DeckError {
+    Io {
+        path: Option<PathBuf>,
+        source: std::io::Error,
+    },
+    Csv {
+        path: Option<PathBuf>,
+        line: Option<u64>,
+        column: Option<usize>,
+        source: csv::Error,
+    },
+    MissingColumn {
+        path: Option<PathBuf>,
+        line: u64,
+        /// One-based, like the line. Formats without columns leave it out.
+        column: Option<usize>,
+        name: String,
+    },
+    /// The deck is not valid in its format, such as malformed JSON or TOML.
+    Parse {
+        path: Option<PathBuf>,
+        line: Option<u64>,
+        column: Option<usize>,
+        message: String,
+    },
+    EmptyDeck {
+        path: Option<PathBuf>,
+    },
+    /// A deck from another application could not be converted.
+    Import {
+        path: Option<PathBuf>,
+        message: String,
+    },
+    DuplicateCard {
+        path: Option<PathBuf>,
+        line: u64,
+        first_line: u64,
+        front: String,
+    },
+}
+
+impl DeckError {
+    pub fn with_path(mut self, deck_path: &Path) -> Self {
+        match &mut self {
+            DeckError::Io { path, .. }
+            | DeckError::Csv { path, .. }
+            | DeckError::MissingColumn { path, .. }
+            | DeckError::Parse { path, .. }
+            | DeckError::EmptyDeck { path }
+            | DeckError::Import { path, .. }
+            | DeckError::DuplicateCard { path, .. } => *path = Some(deck_path.to_path_buf()),
+        }
+
+        self
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            DeckError::Io { source, .. } if source.kind() == ErrorKind::NotFound => EXIT_NO_INPUT,
+            DeckError::Io { .. } => EXIT_IO,
+            _ => EXIT_DATA,
+        }
+    }
+}
+
+/// Renders `path:line:column`, leaving out whatever is unknown.
+fn location(path: &Option<PathBuf>, line: Option<u64>, column: Option<usize>) -> String {
+    let mut location = match path {
+        Some(path) => path.display().to_string(),
+        None => "<deck>".to_string(),
+    };
+
+    if let Some(line) = line {
+        location.push_str(&format!(":{line}"));
+
+        if let Some(column) = column {
+            location.push_str(&format!(":{column}"));
+        }
+    }
+
+    location
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckError::Io { path, source } => {
+                write!(
+                    f,
+                    "{}: could not read deck: {source}",
+                    location(path, None, None)
+                )
+            }
+            DeckError::Csv {
+                path,
+                line,
+                column,
+                source,
+            } => {
+                let location = location(path, *line, *column);
+                match source.kind() {
+                    csv::ErrorKind::Utf8 { .. } => {
+                        write!(f, "{location}: field is not valid UTF-8")
+                    }
+                    _ => write!(f, "{location}: malformed CSV: {source}"),
+                }
+            }
+            DeckError::MissingColumn {
+                path,
+                line,
+                column,
+                name,
+            } => {
+                let location = location(path, Some(*line), None);
+                match column {
+                    Some(column) => write!(
+                        f,
+                        "{location}: row has no \"{name}\" field (expected it in column {column})"
+                    ),
+                    None => write!(f, "{location}: card has no \"{name}\" field"),
+                }
+            }
+            DeckError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}: {message}", location(path, *line, *column)),
+            DeckError::EmptyDeck { path } => {
+                write!(f, "{}: the deck has no cards", location(path, None, None))
+            }
+            DeckError::Import { path, message } => write!(
+                f,
+                "{}: could not import deck: {message}",
+                location(path, None, None)
+            ),
+            DeckError::DuplicateCard {
+                path,
+                line,
+                first_line,
+                front,
+            } => write!(
+                f,
+                "{}: duplicate card \"{front}\" (first seen on line {first_line})",
+                location(path, Some(*line), None)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DeckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeckError::Io { source, .. } => Some(source),
+            DeckError::Csv { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DeckError {
+    fn from(source: std::io::Error) -> Self {
+        DeckError::Io { path: None, source }
+    }
+}
+
+impl From<csv::Error> for DeckError {
+    fn from(source: csv::Error) -> Self {
+        if source.is_io_error() {
+            return DeckError::Io {
+                path: None,
+                source: source.into(),
+            };
+        }
+
+        let (line, column) = match source.kind() {
+            csv::ErrorKind::Utf8 { pos, err } => {
+                (pos.as_ref().map(|pos| pos.line()), Some(err.field() + 1))
+            }
+            _ => (source.position().map(|pos| pos.line()), None),
+        };
+
+        DeckError::Csv {
+            path: None,
+            line,
+            column,
+            source,
+        }
+    }
+}
+
+/// Top level error for the `flashcards` binary.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line could not be understood.
+    Usage(String),
+    /// The config file could not be read or has invalid settings.
+    Config {
+        path: PathBuf,
+        message: String,
+    },
+    Deck(DeckError),
+    /// Failures while running a session, such as reading from stdin or
+    /// saving progress.
+    Io(std::io::Error),
+    /// `validate` found this many problems in the decks.
+    Findings(usize),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Usage(_) => EXIT_USAGE,
+            Error::Config { .. } => EXIT_CONFIG,
+            Error::Deck(err) => err.exit_code(),
+            Error::Io(_) => EXIT_IO,
+            Error::Findings(_) => EXIT_DATA,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{message}"),
+            Error::Config { path, message } => write!(f, "{}: {message}", path.display()),
+            Error::Deck(err) => write!(f, "{err}"),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Findings(1) => write!(f, "found 1 problem"),
+            Error::Findings(count) => write!(f, "found {count} problems"),
+        }
+    }
+}
+
+impl From<DeckError> for Error {
+    fn from(err: DeckError) -> Self {
+        Error::Deck(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let path = Path::new("deck.csv");
+        let cases = vec![
+            (
+                DeckError::EmptyDeck { path: None },
+                "<deck>: the deck has no cards",
+            ),
+            (
+                DeckError::EmptyDeck { path: None }.with_path(path),
+                "deck.csv: the deck has no cards",
+            ),
+            (
+                DeckError::MissingColumn {
+                    path: None,
+                    line: 3,
+                    column: Some(2),
+                    name: "back".to_string(),
+                }
+                .with_path(path),
+                "deck.csv:3: row has no \"back\" field (expected it in column 2)",
+            ),
+            (
+                DeckError::Parse {
+                    path: None,
+                    line: Some(4),
+                    column: Some(9),
+                    message: "invalid TOML: expected a value".to_string(),
+                }
+                .with_path(path),
+                "deck.csv:4:9: invalid TOML: expected a value",
+            ),
+            (
+                DeckError::DuplicateCard {
+                    path: None,
+                    line: 7,
+                    first_line: 2,
+                    front: "front_1".to_string(),
+                }
+                .with_path(path),
+                "deck.csv:7: duplicate card \"front_1\" (first seen on line 2)",
+            ),
+        ];
+
+        for (index, (err, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(err.to_string(), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let not_found = DeckError::from(std::io::Error::from(ErrorKind::NotFound));
+        let denied = DeckError::from(std::io::Error::from(ErrorKind::PermissionDenied));
+
+        assert_eq!(not_found.exit_code(), EXIT_NO_INPUT);
+        assert_eq!(denied.exit_code(), EXIT_IO);
+        assert_eq!(DeckError::EmptyDeck { path: None }.exit_code(), EXIT_DATA);
+        assert_eq!(Error::Usage(String::new()).exit_code(), EXIT_USAGE);
+    }
+}
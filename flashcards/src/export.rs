@@ -0,0 +1,292 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use crate::deck::Deck;
+use crate::error::DeckError;
+use crate::Card;
+
+/// The rows of `decks` that `cards` were made from, as read from the deck
+/// files, for exporting to another deck. A cloze row comes out once and
+/// whole, however many cards it made.
+pub fn rows(decks: &[Deck], cards: &[Card]) -> Vec<Card> {
+    let kept: HashSet<(usize, usize)> = cards.iter().map(|card| (card.deck, card.row)).collect();
+
+    decks
+        .iter()
+        .flat_map(|deck| deck.rows.iter().flatten())
+        .filter(|row| kept.contains(&(row.deck, row.row)))
+        .cloned()
+        .collect()
+}
+
+/// Formats cards can be exported to with `flashcards export --to`. Unlike
+/// the deck formats these are for other tools, or for paper, and are not
+/// read back in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Export {
+    /// CSV with a card per row, including its deck, tags and scheduling.
+    Csv,
+    /// Tab separated text for Anki's "Import File", as Basic notes.
+    Anki,
+    /// An HTML page to print and cut into paper cards.
+    Html,
+}
+
+pub fn parse_export(input: &str) -> Option<Export> {
+    match input {
+        "csv" => Some(Export::Csv),
+        "anki" => Some(Export::Anki),
+        "html" => Some(Export::Html),
+        _ => None,
+    }
+}
+
+impl Export {
+    /// Writes `cards`, each paired with the name of its deck.
+    pub fn write(self, cards: &[(&str, &Card)], writer: &mut dyn Write) -> Result<(), DeckError> {
+        match self {
+            Export::Csv => write_csv(cards, writer),
+            Export::Anki => write_anki(cards, writer),
+            Export::Html => write_html(cards, writer),
+        }
+    }
+}
+
+/// Columns of the CSV export. `box` and `box_due` are the Leitner box.
+const CSV_COLUMNS: [&str; 15] = [
+    "front",
+    "back",
+    "deck",
+    "tags",
+    "hint",
+    "notes",
+    "due",
+    "interval",
+    "ease",
+    "repetitions",
+    "box",
+    "box_due",
+    "correct",
+    "incorrect",
+    "last_seen",
+];
+
+fn write_csv(cards: &[(&str, &Card)], writer: &mut dyn Write) -> Result<(), DeckError> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record(CSV_COLUMNS)?;
+
+    for (deck, card) in cards {
+        let date =
+            |date: Option<chrono::NaiveDate>| date.map_or(String::new(), |date| date.to_string());
+
+        wtr.write_record([
+            card.front().to_string(),
+            card.back().to_string(),
+            deck.to_string(),
+            card.tags.join(" "),
+            card.hint.clone(),
+            card.notes.clone(),
+            date(card.schedule.due),
+            card.schedule.interval.to_string(),
+            format!("{:.2}", card.schedule.ease),
+            card.schedule.repetitions.to_string(),
+            card.leitner.number.to_string(),
+            date(card.leitner.due),
+            card.stats.correct.to_string(),
+            card.stats.incorrect.to_string(),
+            card.stats
+                .last_seen
+                .map_or(String::new(), |seen| seen.to_rfc3339()),
+        ])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Anki reads the `#` lines at the top of the file as import settings.
+/// Fields are HTML, so line breaks survive the trip as `<br>`.
+fn write_anki(cards: &[(&str, &Card)], writer: &mut dyn Write) -> Result<(), DeckError> {
+    writeln!(writer, "#separator:tab")?;
+    writeln!(writer, "#html:true")?;
+    writeln!(writer, "#notetype:Basic")?;
+    writeln!(writer, "#deck column:3")?;
+    writeln!(writer, "#tags column:4")?;
+
+    for (deck, card) in cards {
+        let field = |text: &str| html(text).replace('\t', " ");
+        // Tags in Anki cannot hold spaces, which also separate them here
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            field(card.front()),
+            field(card.back()),
+            field(deck),
+            field(&card.tags.join(" "))
+        )?;
+    }
+
+    Ok(())
+}
+
+/// A page with a card per row: the front on the left and the back on the
+/// right. Cutting along the dashed lines and folding down the middle gives
+/// a paper card with the answer on its back.
+fn write_html(cards: &[(&str, &Card)], writer: &mut dyn Write) -> Result<(), DeckError> {
+    writer.write_all(
+        br#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Flashcards</title>
+<style>
+body { font-family: sans-serif; margin: 1cm; }
+table { border-collapse: collapse; width: 100%; }
+tr { page-break-inside: avoid; }
+td { border: 1px dashed #888; width: 50%; height: 5cm; padding: 0.5cm; text-align: center; vertical-align: middle; }
+td.back { border-left: 1px dotted #ccc; }
+.meta { display: block; margin-top: 0.5em; font-size: 0.7em; color: #666; }
+</style>
+</head>
+<body>
+<table>
+"#,
+    )?;
+
+    for (deck, card) in cards {
+        let mut meta = html(deck);
+        if !card.tags.is_empty() {
+            meta.push_str(&format!(" &middot; {}", html(&card.tags.join(" "))));
+        }
+
+        writeln!(
+            writer,
+            "<tr><td class=\"front\">{}<span class=\"meta\">{meta}</span></td>\
+             <td class=\"back\">{}</td></tr>",
+            html(card.front()),
+            html(card.back())
+        )?;
+    }
+
+    writer.write_all(b"</table>\n</body>\n</html>\n")?;
+    Ok(())
+}
+
+/// Escapes `text` for HTML, turning line breaks into `<br>`.
+fn html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::cloze;
+
+    fn cards() -> Vec<Card> {
+        let mut first = Card::new("What is <T>?".to_string(), "A type\nparameter".to_string());
+        first.tags = vec!["generics".to_string(), "traits".to_string()];
+        first.schedule.due = NaiveDate::from_ymd_opt(2024, 3, 10);
+        first.schedule.interval = 6;
+        first.schedule.repetitions = 2;
+        first.stats.correct = 2;
+        let second = Card::new("a\tb".to_string(), "c".to_string());
+
+        vec![first, second]
+    }
+
+    fn export(export: Export) -> String {
+        let cards = cards();
+        let pairs: Vec<(&str, &Card)> = cards.iter().map(|card| ("rust", card)).collect();
+        let mut output = Vec::new();
+        export.write(&pairs, &mut output).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let cases = vec![
+            ("csv", Some(Export::Csv)),
+            ("anki", Some(Export::Anki)),
+            ("html", Some(Export::Html)),
+            ("pdf", None),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(parse_export(input), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_rows() {
+        let path = std::env::temp_dir().join(format!("flashcards-{}-rows.csv", std::process::id()));
+        let mut deck = Deck::new(path).unwrap();
+        for (index, front) in ["The {{c1::Ord}} trait needs {{c2::Eq}}", "left out"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut row = Card::new(front.to_string(), String::new());
+            row.row = index;
+            deck.rows.push(Some(row));
+        }
+
+        // Both deletions of the cloze row passed the filters
+        let cards = cloze::expand(deck.rows[0].clone().unwrap());
+        assert_eq!(cards.len(), 2);
+
+        let exported = rows(&[deck], &cards);
+        assert_eq!(exported.len(), 1);
+        assert_eq!(
+            exported[0].front(),
+            "The {{c1::Ord}} trait needs {{c2::Eq}}"
+        );
+        assert_eq!(exported[0].id, None);
+    }
+
+    #[test]
+    fn test_write_csv() {
+        assert_eq!(
+            export(Export::Csv),
+            "\
+front,back,deck,tags,hint,notes,due,interval,ease,repetitions,box,box_due,correct,incorrect,last_seen
+What is <T>?,\"A type
+parameter\",rust,generics traits,,,2024-03-10,6,2.50,2,1,,2,0,
+a\tb,c,rust,,,,,0,2.50,0,1,,0,0,
+"
+        );
+    }
+
+    #[test]
+    fn test_write_anki() {
+        assert_eq!(
+            export(Export::Anki),
+            "\
+#separator:tab
+#html:true
+#notetype:Basic
+#deck column:3
+#tags column:4
+What is &lt;T&gt;?\tA type<br>parameter\trust\tgenerics traits
+a b\tc\trust\t
+"
+        );
+    }
+
+    #[test]
+    fn test_write_html() {
+        let html = export(Export::Html);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(
+            "<tr><td class=\"front\">What is &lt;T&gt;?<span class=\"meta\">rust &middot; \
+             generics traits</span></td><td class=\"back\">A type<br>parameter</td></tr>\n"
+        ));
+        assert!(html.ends_with("</table>\n</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,313 @@
+use std::io::{Read, Write};
+
+use super::{card_from_fields, check_deck, field_names, field_value, header, DeckFormat};
+use crate::error::DeckError;
+use crate::Card;
+
+/// Delimited text whose first row names the columns: CSV, or TSV with a tab
+/// as the delimiter.
+///
+/// `front` and `back` fall back to the first two columns when the header
+/// does not name them. Every row needs both of those, but may stop before
+/// the optional columns, which are then read as empty.
+pub struct CsvFormat {
+    delimiter: u8,
+}
+
+impl CsvFormat {
+    pub fn csv() -> Self {
+        Self { delimiter: b',' }
+    }
+
+    pub fn tsv() -> Self {
+        Self { delimiter: b'\t' }
+    }
+}
+
+impl DeckFormat for CsvFormat {
+    fn load(&self, reader: &mut dyn Read) -> Result<Vec<Card>, DeckError> {
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .flexible(true)
+            .from_reader(reader);
+
+        let original: Vec<String> = rdr
+            .headers()?
+            .iter()
+            .map(|header| header.trim().to_string())
+            .collect();
+        let mut headers = original.clone();
+        let position = |headers: &[String], name: &str| {
+            headers
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(name))
+        };
+
+        // A column named for one side is not also taken as the other
+        let named_back = position(&headers, "back");
+        let front_column =
+            position(&headers, "front").unwrap_or(if named_back == Some(0) { 1 } else { 0 });
+        let back_column = named_back.unwrap_or(if front_column == 1 { 0 } else { 1 });
+        for (column, name) in [(front_column, "front"), (back_column, "back")] {
+            if let Some(header) = headers.get_mut(column) {
+                *header = name.to_string();
+            }
+        }
+
+        let mut cards = Vec::new();
+        for result in rdr.records() {
+            let record = result?;
+            let line = record.position().map_or(0, |pos| pos.line());
+
+            for (column, name) in [(front_column, "front"), (back_column, "back")] {
+                if record.get(column).is_none() {
+                    return Err(DeckError::MissingColumn {
+                        path: None,
+                        line,
+                        column: Some(column + 1),
+                        name: name.to_string(),
+                    });
+                }
+            }
+
+            let fields = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(header, value)| (header.clone(), value.to_string()));
+
+            let mut card = card_from_fields(fields);
+            // The columns taken as the front and back keep their own names
+            for (name, header) in &mut card.fields {
+                match name.as_str() {
+                    "front" => header.clone_from(&original[front_column]),
+                    "back" => header.clone_from(&original[back_column]),
+                    _ => {}
+                }
+            }
+
+            cards.push((line, card));
+        }
+
+        check_deck(cards)
+    }
+
+    /// Optional columns are only written when a card uses them, and extra
+    /// columns follow in the order they were read.
+    fn write(&self, cards: &[Card], writer: &mut dyn Write) -> Result<(), DeckError> {
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(writer);
+
+        let names = field_names(cards);
+        wtr.write_record(names.iter().map(|name| header(cards, name)))?;
+
+        for card in cards {
+            wtr.write_record(names.iter().map(|name| field_value(card, name)))?;
+        }
+
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress;
+
+    fn load_from_csv(data: &[u8]) -> Result<Vec<Card>, DeckError> {
+        CsvFormat::csv().load(&mut &data[..])
+    }
+
+    #[test]
+    fn test_load_from_csv() {
+        let data = "\
+front, back,
+front_1, back_1,
+front_2, back_2,
+front_3, back_3,
+";
+
+        let cards = load_from_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(cards[0].front(), "front_1");
+        assert_eq!(cards[0].back(), "back_1");
+        assert_eq!(cards[1].front(), "front_2");
+        assert_eq!(cards[1].back(), "back_2");
+        assert_eq!(cards[2].front(), "front_3");
+        assert_eq!(cards[2].back(), "back_3");
+    }
+
+    #[test]
+    fn test_load_from_csv_schema() {
+        let data = "\
+Back,Front,Source,tags,hint,notes,reverse,id
+b1,f1,book,rust traits,think Ord,see ch 3,yes,card-1
+b2,f2,,,,,,
+";
+
+        let cards = load_from_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(cards[0].front(), "f1");
+        assert_eq!(cards[0].back(), "b1");
+        assert_eq!(cards[0].tags, vec!["rust", "traits"]);
+        assert_eq!(cards[0].hint, "think Ord");
+        assert_eq!(cards[0].notes, "see ch 3");
+        assert!(cards[0].reverse);
+        assert_eq!(cards[0].id(), "card-1");
+        assert_eq!(
+            cards[0].extra,
+            vec![("Source".to_string(), "book".to_string())]
+        );
+
+        assert!(cards[1].tags.is_empty());
+        assert!(!cards[1].reverse);
+        assert_eq!(cards[1].id(), progress::card_id("f2", "b2"));
+    }
+
+    #[test]
+    fn test_load_from_csv_unnamed_columns() {
+        let data = "\
+question,answer,source
+f1,b1,book
+";
+
+        let cards = load_from_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(cards[0].front(), "f1");
+        assert_eq!(cards[0].back(), "b1");
+        assert_eq!(
+            cards[0].extra,
+            vec![("source".to_string(), "book".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_load_from_csv_columns() {
+        let cases = vec![
+            ("question,answer\nf1,b1\n", ("f1", "b1")),
+            ("answer,front\nb1,f1\n", ("f1", "b1")),
+            ("back,question\nb1,f1\n", ("f1", "b1")),
+            ("source,notes,front\nbook,b1,f1\n", ("f1", "b1")),
+            ("Back,Front\nb1,f1\n", ("f1", "b1")),
+        ];
+
+        for (index, (data, (front, back))) in cases.into_iter().enumerate() {
+            let cards = load_from_csv(data.as_bytes()).unwrap();
+            assert_eq!(cards[0].front(), front, "Case {index} failed");
+            assert_eq!(cards[0].back(), back, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_load_from_csv_short_rows() {
+        let data = "\
+front,back,hint,notes
+front_1,back_1
+front_2,back_2,a hint
+";
+
+        let cards = load_from_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(cards[0].back(), "back_1");
+        assert_eq!(cards[0].hint, "");
+        assert_eq!(cards[1].hint, "a hint");
+        assert_eq!(cards[1].notes, "");
+    }
+
+    #[test]
+    fn test_load_from_csv_errors() {
+        let cases = vec![
+            ("front,back\n", "<deck>: the deck has no cards"),
+            (
+                "front,back\nfront_1,back_1\nfront_2\n",
+                "<deck>:3: row has no \"back\" field (expected it in column 2)",
+            ),
+            (
+                "back,front\nback_1\n",
+                "<deck>:2: row has no \"front\" field (expected it in column 2)",
+            ),
+            (
+                "front,back\nfront_1,back_1\nfront_2,back_2\nfront_1,back_1\n",
+                "<deck>:4: duplicate card \"front_1\" (first seen on line 2)",
+            ),
+        ];
+
+        for (index, (data, expected)) in cases.into_iter().enumerate() {
+            let err = load_from_csv(data.as_bytes()).err().unwrap();
+            assert_eq!(err.to_string(), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_load_from_csv_invalid_utf8() {
+        let data = b"front,back\nfront_1,back_\xff\n";
+
+        let err = load_from_csv(data).err().unwrap();
+
+        assert_eq!(err.to_string(), "<deck>:2:2: field is not valid UTF-8");
+    }
+
+    #[test]
+    fn test_write_csv_round_trip() {
+        let data = "\
+front,back,tags,hint,Source,Page
+f1,b1,rust,think Ord,book,12
+f2,b2,,,,
+";
+
+        let cards = load_from_csv(data.as_bytes()).unwrap();
+        let mut output = Vec::new();
+        CsvFormat::csv().write(&cards, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), data);
+    }
+
+    #[test]
+    fn test_write_csv_keeps_headers() {
+        let cases = vec![
+            (
+                "question,answer
+f1,b1
+",
+                "question,answer,flagged
+f1,b1,true
+",
+            ),
+            (
+                "Back,Front,Tags
+b1,f1,rust
+",
+                "Back,Front,Tags,flagged
+b1,f1,rust,true
+",
+            ),
+        ];
+
+        for (index, (data, expected)) in cases.into_iter().enumerate() {
+            let mut cards = load_from_csv(data.as_bytes()).unwrap();
+            cards[0].flagged = true;
+            let mut output = Vec::new();
+            CsvFormat::csv().write(&cards, &mut output).unwrap();
+
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tsv_round_trip() {
+        let data = "front\tback\tnotes\nf1\tb1, with a comma\tn1\n";
+
+        let cards = CsvFormat::tsv().load(&mut data.as_bytes()).unwrap();
+        assert_eq!(cards[0].back(), "b1, with a comma");
+
+        let mut output = Vec::new();
+        CsvFormat::tsv().write(&cards, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), data);
+    }
+}
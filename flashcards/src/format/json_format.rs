@@ -0,0 +1,200 @@
+use std::io::{Read, Write};
+
+use serde_json::{Map, Value};
+
+use super::{card_from_fields, check_deck, field_names, field_value, flag, DeckFormat};
+use crate::error::DeckError;
+use crate::Card;
+
+/// A JSON array of card objects:
+///
+/// ```json
+/// [{"front": "Ord", "back": "A total order", "tags": ["traits"]}]
+/// ```
+///
+/// `tags` may be an array or a space separated string. Positions in errors
+/// are the card's number in the array.
+pub struct JsonFormat;
+
+/// Flattens a JSON value into the text form the other formats use.
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values.iter().map(value_text).collect::<Vec<_>>().join(" "),
+        other => other.to_string(),
+    }
+}
+
+impl DeckFormat for JsonFormat {
+    fn load(&self, reader: &mut dyn Read) -> Result<Vec<Card>, DeckError> {
+        let value: Value = serde_json::from_reader(reader).map_err(|err| {
+            if err.is_io() {
+                return DeckError::from(std::io::Error::from(err));
+            }
+
+            DeckError::Parse {
+                path: None,
+                line: Some(err.line() as u64),
+                column: Some(err.column()),
+                message: format!("invalid JSON: {err}"),
+            }
+        })?;
+
+        let Value::Array(items) = value else {
+            return Err(DeckError::Parse {
+                path: None,
+                line: None,
+                column: None,
+                message: "expected a JSON array of cards".to_string(),
+            });
+        };
+
+        let mut cards = Vec::new();
+        for (index, item) in items.into_iter().enumerate() {
+            let number = index as u64 + 1;
+
+            let Value::Object(object) = item else {
+                return Err(DeckError::Parse {
+                    path: None,
+                    line: Some(number),
+                    column: None,
+                    message: format!("card {number} is not an object"),
+                });
+            };
+
+            // Named in any case, as CSV headers are
+            for name in ["front", "back"] {
+                if !object.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+                    return Err(DeckError::MissingColumn {
+                        path: None,
+                        line: number,
+                        column: None,
+                        name: name.to_string(),
+                    });
+                }
+            }
+
+            let fields = object
+                .iter()
+                .map(|(name, value)| (name.clone(), value_text(value)));
+            cards.push((number, card_from_fields(fields)));
+        }
+
+        check_deck(cards)
+    }
+
+    fn write(&self, cards: &[Card], writer: &mut dyn Write) -> Result<(), DeckError> {
+        let names = field_names(cards);
+
+        let items: Vec<Value> = cards
+            .iter()
+            .map(|card| {
+                let mut object = Map::new();
+
+                for name in &names {
+                    let value = match (name.as_str(), flag(card, name)) {
+                        ("tags", _) => Value::from(card.tags.clone()),
+                        (_, Some(set)) => Value::from(set),
+                        _ => Value::from(field_value(card, name)),
+                    };
+
+                    // Only front and back are written for every card
+                    let is_empty = match &value {
+                        Value::String(text) => text.is_empty(),
+                        Value::Array(values) => values.is_empty(),
+                        Value::Bool(flag) => !flag,
+                        _ => false,
+                    };
+                    if !is_empty || name == "front" || name == "back" {
+                        object.insert(name.clone(), value);
+                    }
+                }
+
+                Value::Object(object)
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(&mut *writer, &items).map_err(std::io::Error::from)?;
+        writeln!(writer)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_json() {
+        let data = r#"[
+            {"front": "f1", "back": "b1", "tags": ["a", "b"], "reverse": true, "source": "book"},
+            {"Front": "f2", "BACK": "b2", "tags": "c d", "page": 12}
+        ]"#;
+
+        let cards = JsonFormat.load(&mut data.as_bytes()).unwrap();
+
+        assert_eq!(cards[0].front(), "f1");
+        assert_eq!(cards[0].back(), "b1");
+        assert_eq!(cards[0].tags, vec!["a", "b"]);
+        assert!(cards[0].reverse);
+        assert_eq!(
+            cards[0].extra,
+            vec![("source".to_string(), "book".to_string())]
+        );
+        assert_eq!(cards[1].front(), "f2");
+        assert_eq!(cards[1].back(), "b2");
+        assert_eq!(cards[1].tags, vec!["c", "d"]);
+        assert_eq!(cards[1].extra, vec![("page".to_string(), "12".to_string())]);
+    }
+
+    #[test]
+    fn test_load_json_errors() {
+        let cases = vec![
+            (
+                "[",
+                "<deck>:1:1: invalid JSON: EOF while parsing a list at line 1 column 1",
+            ),
+            ("{}", "<deck>: expected a JSON array of cards"),
+            ("[1]", "<deck>:1: card 1 is not an object"),
+            (
+                r#"[{"front": "f1"}]"#,
+                "<deck>:1: card has no \"back\" field",
+            ),
+            ("[]", "<deck>: the deck has no cards"),
+        ];
+
+        for (index, (data, expected)) in cases.into_iter().enumerate() {
+            let err = JsonFormat.load(&mut data.as_bytes()).err().unwrap();
+            assert_eq!(err.to_string(), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let data = r#"[
+  {
+    "front": "f1",
+    "back": "b1",
+    "tags": [
+      "a",
+      "b"
+    ],
+    "reverse": true,
+    "source": "book"
+  },
+  {
+    "front": "f2",
+    "back": "b2"
+  }
+]
+"#;
+
+        let cards = JsonFormat.load(&mut data.as_bytes()).unwrap();
+        let mut output = Vec::new();
+        JsonFormat.write(&cards, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), data);
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::{Read, Write};
+
+use super::{check_deck, DeckFormat};
+use crate::error::DeckError;
+use crate::Card;
+
+/// A Markdown question and answer list. Every `## ` heading is the front of
+/// a card and the text below it, up to the next such heading, is the back:
+///
+/// ```markdown
+/// ## What does `Ord` require?
+///
+/// `Eq` and `PartialOrd`.
+/// ```
+///
+/// Anything before the first heading, such as a `#` title, is ignored, and
+/// headings inside fenced code blocks are part of the back. Only the front
+/// and back of a card are kept in this format.
+pub struct MarkdownFormat;
+
+impl DeckFormat for MarkdownFormat {
+    fn load(&self, reader: &mut dyn Read) -> Result<Vec<Card>, DeckError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let mut cards = Vec::new();
+        let mut current: Option<(u64, String, Vec<&str>)> = None;
+        let mut in_fence = false;
+
+        let mut finish = |current: Option<(u64, String, Vec<&str>)>| {
+            if let Some((line, front, body)) = current {
+                let back = body.join("\n").trim().to_string();
+                cards.push((line, Card::new(front, back)));
+            }
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+
+            match line.strip_prefix("## ") {
+                Some(front) if !in_fence => {
+                    finish(current.take());
+                    current = Some((index as u64 + 1, front.trim().to_string(), Vec::new()));
+                }
+                _ => {
+                    if let Some((_, _, body)) = current.as_mut() {
+                        body.push(line);
+                    }
+                }
+            }
+        }
+        finish(current);
+
+        check_deck(cards)
+    }
+
+    fn write(&self, cards: &[Card], writer: &mut dyn Write) -> Result<(), DeckError> {
+        for (index, card) in cards.iter().enumerate() {
+            if index > 0 {
+                writeln!(writer)?;
+            }
+
+            // A heading has to stay on one line
+            let front = card.front().lines().collect::<Vec<_>>().join(" ");
+            writeln!(writer, "## {front}")?;
+            if !card.back().is_empty() {
+                writeln!(writer, "\n{}", card.back())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_markdown() {
+        let data = "\
+# Rust traits
+
+Some intro text.
+
+## What does `Ord` require?
+
+`Eq` and `PartialOrd`.
+
+## Show a derive
+
+```rust
+## not a heading
+#[derive(PartialEq)]
+```
+## Empty back
+";
+
+        let cards = MarkdownFormat.load(&mut data.as_bytes()).unwrap();
+
+        assert_eq!(cards.len(), 3);
+        assert_eq!(cards[0].front(), "What does `Ord` require?");
+        assert_eq!(cards[0].back(), "`Eq` and `PartialOrd`.");
+        assert_eq!(cards[1].front(), "Show a derive");
+        assert_eq!(
+            cards[1].back(),
+            "```rust\n## not a heading\n#[derive(PartialEq)]\n```"
+        );
+        assert_eq!(cards[2].back(), "");
+    }
+
+    #[test]
+    fn test_load_markdown_errors() {
+        let cases = vec![
+            ("# Only a title\n", "<deck>: the deck has no cards"),
+            (
+                "## f1\nb1\n## f2\nb2\n## f1\nb1\n",
+                "<deck>:5: duplicate card \"f1\" (first seen on line 1)",
+            ),
+        ];
+
+        for (index, (data, expected)) in cases.into_iter().enumerate() {
+            let err = MarkdownFormat.load(&mut data.as_bytes()).err().unwrap();
+            assert_eq!(err.to_string(), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_markdown_round_trip() {
+        let data = "## f1\n\nb1\nsecond line\n\n## f2\n\nb2\n";
+
+        let cards = MarkdownFormat.load(&mut data.as_bytes()).unwrap();
+        let mut output = Vec::new();
+        MarkdownFormat.write(&cards, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), data);
+    }
+}
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::anki::{AnkiFormat, FieldMapping};
+use crate::error::DeckError;
+use crate::Card;
+
+mod csv_format;
+mod json_format;
+mod markdown_format;
+mod toml_format;
+
+pub use csv_format::CsvFormat;
+pub use json_format::JsonFormat;
+pub use markdown_format::MarkdownFormat;
+pub use toml_format::TomlFormat;
+
+/// A file format decks can be read from and written to.
+pub trait DeckFormat {
+    fn load(&self, reader: &mut dyn Read) -> Result<Vec<Card>, DeckError>;
+
+    fn write(&self, cards: &[Card], writer: &mut dyn Write) -> Result<(), DeckError>;
+}
+
+/// The formats known to the command line, picked by file extension or with
+/// `--format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Json,
+    Toml,
+    Markdown,
+    Anki,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "tsv" => Some(Format::Tsv),
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "md" | "markdown" => Some(Format::Markdown),
+            "apkg" | "anki" => Some(Format::Anki),
+            _ => None,
+        }
+    }
+
+    /// Whether decks in this format can be written back without losing
+    /// anything. Markdown only keeps the front and back of cards, and Anki
+    /// packages cannot be written at all.
+    pub fn round_trips(self) -> bool {
+        !matches!(self, Format::Markdown | Format::Anki)
+    }
+
+    /// Guesses the format from the file extension, falling back to CSV.
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|extension| Format::parse(&extension.to_string_lossy()))
+            .unwrap_or(Format::Csv)
+    }
+
+    fn deck_format(self, mappings: &[FieldMapping]) -> Box<dyn DeckFormat> {
+        match self {
+            Format::Csv => Box::new(CsvFormat::csv()),
+            Format::Tsv => Box::new(CsvFormat::tsv()),
+            Format::Json => Box::new(JsonFormat),
+            Format::Toml => Box::new(TomlFormat),
+            Format::Markdown => Box::new(MarkdownFormat),
+            Format::Anki => Box::new(AnkiFormat::new(mappings.to_vec())),
+        }
+    }
+}
+
+/// Opens and loads the deck at `path`, attaching the path to any error.
+pub fn load_deck(
+    path: &Path,
+    format: Option<Format>,
+    mappings: &[FieldMapping],
+) -> Result<Vec<Card>, DeckError> {
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+
+    File::open(path)
+        .map_err(DeckError::from)
+        .and_then(|mut file| format.deck_format(mappings).load(&mut file))
+        .map_err(|err| err.with_path(path))
+}
+
+/// Writes `cards` to `path` in the given format, or the one its extension
+/// names. The deck is written to a temporary file that then replaces the
+/// old one, so a failed write never leaves a deck half written.
+pub fn write_deck(path: &Path, format: Option<Format>, cards: &[Card]) -> Result<(), DeckError> {
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    File::create(&tmp_path)
+        .map_err(DeckError::from)
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            format.deck_format(&[]).write(cards, &mut writer)?;
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            drop(writer);
+            std::fs::rename(&tmp_path, path)?;
+            Ok(())
+        })
+        .map_err(|err| {
+            let _ = std::fs::remove_file(&tmp_path);
+            err.with_path(path)
+        })
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(
+        value.to_lowercase().as_str(),
+        "1" | "y" | "yes" | "true" | "x"
+    )
+}
+
+/// Builds a card out of named fields. `front`, `back`, `id`, `tags`, `hint`,
+/// `notes`, `reverse`, `suspended` and `flagged` are recognized in any case;
+/// every other field is kept as an extra.
+fn card_from_fields(fields: impl IntoIterator<Item = (String, String)>) -> Card {
+    let mut card = Card::new(String::new(), String::new());
+
+    for (name, value) in fields {
+        let value = value.trim().to_string();
+        let known = name.to_lowercase();
+
+        if !name.is_empty() {
+            let field = if FIELDS.contains(&known.as_str()) {
+                known.clone()
+            } else {
+                name.clone()
+            };
+            card.fields.push((field, name.clone()));
+        }
+
+        match known.as_str() {
+            "front" => card.front = value,
+            "back" => card.back = value,
+            "id" => card.id = Some(value).filter(|id| !id.is_empty()),
+            "tags" => card.tags = value.split_whitespace().map(str::to_string).collect(),
+            "hint" => card.hint = value,
+            "notes" => card.notes = value,
+            "reverse" => card.reverse = parse_bool(&value),
+            "suspended" => card.suspended = parse_bool(&value),
+            "flagged" => card.flagged = parse_bool(&value),
+            // Unnamed columns, usually from trailing commas, carry nothing
+            "" => {}
+            _ => card.extra.push((name, value)),
+        }
+    }
+
+    card
+}
+
+/// The fields known to [`card_from_fields`], in the order writers put them
+/// for cards that were not read from a file.
+const FIELDS: [&str; 9] = [
+    "front",
+    "back",
+    "id",
+    "tags",
+    "hint",
+    "notes",
+    "reverse",
+    "suspended",
+    "flagged",
+];
+
+/// The fields used by any of the cards, in the order writers put them: the
+/// fields of the file the cards were read from, keeping its column order,
+/// followed by `front` and `back`, the optional fields the cards use, then
+/// extras as they were read.
+fn field_names(cards: &[Card]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (name, _) in cards.iter().flat_map(|card| &card.fields) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    for (used, name) in [
+        (true, "front"),
+        (true, "back"),
+        (cards.iter().any(|card| card.id.is_some()), "id"),
+        (cards.iter().any(|card| !card.tags.is_empty()), "tags"),
+        (cards.iter().any(|card| !card.hint.is_empty()), "hint"),
+        (cards.iter().any(|card| !card.notes.is_empty()), "notes"),
+        (cards.iter().any(|card| card.reverse), "reverse"),
+        (cards.iter().any(|card| card.suspended), "suspended"),
+        (cards.iter().any(|card| card.flagged), "flagged"),
+    ] {
+        if used && !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+
+    for (name, _) in cards.iter().flat_map(|card| &card.extra) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    names
+}
+
+/// The header a field is written under in CSV and TSV: the one the file it
+/// was read from gives it, or its name. Other formats always use the name,
+/// since their loaders look fields up by it.
+fn header<'a>(cards: &'a [Card], name: &'a str) -> &'a str {
+    cards
+        .iter()
+        .flat_map(|card| &card.fields)
+        .find(|(field, _)| field == name)
+        .map_or(name, |(_, header)| header.as_str())
+}
+
+/// A card's value for one of the names from [`field_names`], as text.
+fn field_value(card: &Card, name: &str) -> String {
+    match name {
+        "front" => card.front.clone(),
+        "back" => card.back.clone(),
+        "id" => card.id.clone().unwrap_or_default(),
+        "tags" => card.tags.join(" "),
+        "hint" => card.hint.clone(),
+        "notes" => card.notes.clone(),
+        "reverse" | "suspended" | "flagged" => {
+            if flag(card, name) == Some(true) {
+                "true".to_string()
+            } else {
+                String::new()
+            }
+        }
+        _ => card
+            .extra
+            .iter()
+            .find(|(extra_name, _)| extra_name == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default(),
+    }
+}
+
+/// A card's value for one of the true or false fields, or `None` for the
+/// names of other fields.
+fn flag(card: &Card, name: &str) -> Option<bool> {
+    match name {
+        "reverse" => Some(card.reverse),
+        "suspended" => Some(card.suspended),
+        "flagged" => Some(card.flagged),
+        _ => None,
+    }
+}
+
+/// Checks the loaded cards make a usable deck: there is at least one, and no
+/// two share an id, since progress is keyed by it.
+///
+/// Each card comes with the line it starts on, or its position in the deck
+/// for formats where lines mean little.
+fn check_deck(cards: Vec<(u64, Card)>) -> Result<Vec<Card>, DeckError> {
+    if cards.is_empty() {
+        return Err(DeckError::EmptyDeck { path: None });
+    }
+
+    let mut seen = HashMap::new();
+    for (line, card) in &cards {
+        if let Some(first_line) = seen.insert(card.id(), *line) {
+            return Err(DeckError::DuplicateCard {
+                path: None,
+                line: *line,
+                first_line,
+                front: card.front.clone(),
+            });
+        }
+    }
+
+    Ok(cards.into_iter().map(|(_, card)| card).collect())
+}
+
+/// Line and column (both one-based) of a byte offset into `text`.
+fn line_and_column(text: &str, offset: usize) -> (u64, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+
+    (line as u64, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        let cases = vec![
+            ("deck.csv", Format::Csv),
+            ("deck.TSV", Format::Tsv),
+            ("deck.json", Format::Json),
+            ("deck.toml", Format::Toml),
+            ("deck.md", Format::Markdown),
+            ("deck.markdown", Format::Markdown),
+            ("deck.apkg", Format::Anki),
+            ("deck.txt", Format::Csv),
+            ("deck", Format::Csv),
+        ];
+
+        for (index, (path, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                Format::from_path(Path::new(path)),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_card_from_fields() {
+        let card = card_from_fields(fields(&[
+            ("Front", " f1 "),
+            ("back", "b1"),
+            ("tags", "a  b"),
+            ("reverse", "yes"),
+            ("", "ignored"),
+            ("Source", "book"),
+        ]));
+
+        assert_eq!(card.front(), "f1");
+        assert_eq!(card.back(), "b1");
+        assert_eq!(card.tags, vec!["a", "b"]);
+        assert!(card.reverse);
+        assert_eq!(card.id, None);
+        assert_eq!(card.extra, fields(&[("Source", "book")]));
+    }
+
+    #[test]
+    fn test_field_names() {
+        let mut first = card_from_fields(fields(&[("front", "f1"), ("back", "b1"), ("b", "1")]));
+        first.hint = "hint".to_string();
+        let second = card_from_fields(fields(&[("front", "f2"), ("back", "b2"), ("a", "2")]));
+
+        assert_eq!(
+            field_names(&[first, second]),
+            vec!["front", "back", "b", "a", "hint"]
+        );
+
+        let mut card = Card::new("f".to_string(), "b".to_string());
+        card.flagged = true;
+        assert_eq!(field_names(&[card]), vec!["front", "back", "flagged"]);
+    }
+
+    #[test]
+    fn test_field_names_keep_file_order() {
+        let card = card_from_fields(fields(&[
+            ("Source", "book"),
+            ("Back", "b1"),
+            ("notes", ""),
+            ("FRONT", "f1"),
+            ("", ""),
+        ]));
+
+        assert_eq!(
+            field_names(&[card]),
+            vec!["Source", "back", "notes", "front"]
+        );
+    }
+
+    #[test]
+    fn test_round_trip_across_formats() {
+        let cases = vec![
+            ("Front,Back,Tags\nf1,b1,rust\n", Format::Json),
+            ("Front,Back,Tags\nf1,b1,rust\n", Format::Toml),
+            ("Question,Answer\nf1,b1\n", Format::Json),
+            ("Question,Answer\nf1,b1\n", Format::Toml),
+        ];
+
+        for (index, (data, format)) in cases.into_iter().enumerate() {
+            let cards = CsvFormat::csv().load(&mut data.as_bytes()).unwrap();
+            let mut output = Vec::new();
+            format.deck_format(&[]).write(&cards, &mut output).unwrap();
+            let loaded = format.deck_format(&[]).load(&mut &output[..]).unwrap();
+
+            assert_eq!(loaded[0].front(), "f1", "Case {index} failed");
+            assert_eq!(loaded[0].back(), "b1", "Case {index} failed");
+            assert_eq!(loaded[0].tags, cards[0].tags, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_check_deck() {
+        let card = |front: &str| Card::new(front.to_string(), "back".to_string());
+
+        assert!(check_deck(vec![(1, card("a")), (2, card("b"))]).is_ok());
+        assert_eq!(
+            check_deck(vec![]).err().unwrap().to_string(),
+            "<deck>: the deck has no cards"
+        );
+        assert_eq!(
+            check_deck(vec![(3, card("a")), (8, card("a"))])
+                .err()
+                .unwrap()
+                .to_string(),
+            "<deck>:8: duplicate card \"a\" (first seen on line 3)"
+        );
+    }
+
+    #[test]
+    fn test_load_deck_missing_file() {
+        let err = load_deck(Path::new("does/not/exist.csv"), None, &[])
+            .err()
+            .unwrap();
+
+        assert!(err
+            .to_string()
+            .starts_with("does/not/exist.csv: could not read deck:"));
+        assert_eq!(err.exit_code(), crate::error::EXIT_NO_INPUT);
+    }
+
+    #[test]
+    fn test_line_and_column() {
+        let text = "ab\ncde\nf";
+        let cases = vec![
+            (0, (1, 1)),
+            (1, (1, 2)),
+            (3, (2, 1)),
+            (5, (2, 3)),
+            (7, (3, 1)),
+        ];
+
+        for (index, (offset, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                line_and_column(text, offset),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+}
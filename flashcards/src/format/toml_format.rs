@@ -0,0 +1,216 @@
+use std::io::{Read, Write};
+
+use toml::{Table, Value};
+
+use super::{
+    card_from_fields, check_deck, field_names, field_value, flag, line_and_column, DeckFormat,
+};
+use crate::error::DeckError;
+use crate::Card;
+
+/// A TOML document with one `[[card]]` table per card:
+///
+/// ```toml
+/// [[card]]
+/// front = "Ord"
+/// back = "A total order"
+/// tags = ["traits"]
+/// ```
+///
+/// Positions in errors other than syntax errors are the card's number.
+pub struct TomlFormat;
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values.iter().map(value_text).collect::<Vec<_>>().join(" "),
+        other => other.to_string(),
+    }
+}
+
+fn parse_error(message: String) -> DeckError {
+    DeckError::Parse {
+        path: None,
+        line: None,
+        column: None,
+        message,
+    }
+}
+
+impl DeckFormat for TomlFormat {
+    fn load(&self, reader: &mut dyn Read) -> Result<Vec<Card>, DeckError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+
+        let table: Table = toml::from_str(&text).map_err(|err| {
+            let (line, column) = match err.span() {
+                Some(span) => {
+                    let (line, column) = line_and_column(&text, span.start);
+                    (Some(line), Some(column))
+                }
+                None => (None, None),
+            };
+
+            DeckError::Parse {
+                path: None,
+                line,
+                column,
+                message: format!("invalid TOML: {}", err.message()),
+            }
+        })?;
+
+        let items = match table.get("card") {
+            Some(Value::Array(items)) => items.as_slice(),
+            Some(_) => return Err(parse_error("`card` must be an array of tables".to_string())),
+            None => &[],
+        };
+
+        let mut cards = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let number = index as u64 + 1;
+
+            let Value::Table(table) = item else {
+                return Err(parse_error(format!("card {number} is not a table")));
+            };
+
+            // Named in any case, as CSV headers are
+            for name in ["front", "back"] {
+                if !table.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+                    return Err(DeckError::MissingColumn {
+                        path: None,
+                        line: number,
+                        column: None,
+                        name: name.to_string(),
+                    });
+                }
+            }
+
+            let fields = table
+                .iter()
+                .map(|(name, value)| (name.clone(), value_text(value)));
+            cards.push((number, card_from_fields(fields)));
+        }
+
+        check_deck(cards)
+    }
+
+    fn write(&self, cards: &[Card], writer: &mut dyn Write) -> Result<(), DeckError> {
+        let names = field_names(cards);
+
+        let items: Vec<Value> = cards
+            .iter()
+            .map(|card| {
+                let mut table = Table::new();
+
+                for name in &names {
+                    match (name.as_str(), flag(card, name)) {
+                        ("tags", _) if !card.tags.is_empty() => {
+                            table.insert(name.clone(), Value::from(card.tags.clone()));
+                        }
+                        (_, Some(true)) => {
+                            table.insert(name.clone(), Value::from(true));
+                        }
+                        ("tags", _) | (_, Some(false)) => {}
+                        _ => {
+                            let value = field_value(card, name);
+
+                            // Only front and back are written for every card
+                            if !value.is_empty() || name == "front" || name == "back" {
+                                table.insert(name.clone(), Value::from(value));
+                            }
+                        }
+                    }
+                }
+
+                Value::Table(table)
+            })
+            .collect();
+
+        let mut document = Table::new();
+        document.insert("card".to_string(), Value::Array(items));
+
+        let text = toml::to_string(&document).map_err(std::io::Error::other)?;
+        writer.write_all(text.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_toml() {
+        let data = r#"
+[[card]]
+front = "f1"
+back = "b1"
+tags = ["a", "b"]
+reverse = true
+source = "book"
+
+[[card]]
+Front = "f2"
+Back = """
+multi
+line"""
+page = 12
+"#;
+
+        let cards = TomlFormat.load(&mut data.as_bytes()).unwrap();
+
+        assert_eq!(cards[0].front(), "f1");
+        assert_eq!(cards[0].tags, vec!["a", "b"]);
+        assert!(cards[0].reverse);
+        assert_eq!(
+            cards[0].extra,
+            vec![("source".to_string(), "book".to_string())]
+        );
+        assert_eq!(cards[1].front(), "f2");
+        assert_eq!(cards[1].back(), "multi\nline");
+        assert_eq!(cards[1].extra, vec![("page".to_string(), "12".to_string())]);
+    }
+
+    #[test]
+    fn test_load_toml_errors() {
+        let cases = vec![
+            ("[[card]]\nfront = ", "<deck>:2:9: invalid TOML: "),
+            ("card = 1", "<deck>: `card` must be an array of tables"),
+            (
+                "[[card]]\nback = \"b1\"",
+                "<deck>:1: card has no \"front\" field",
+            ),
+            ("", "<deck>: the deck has no cards"),
+        ];
+
+        for (index, (data, expected)) in cases.into_iter().enumerate() {
+            let err = TomlFormat.load(&mut data.as_bytes()).err().unwrap();
+            assert!(
+                err.to_string().starts_with(expected),
+                "Case {index} failed: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let data = r#"[[card]]
+front = "f1"
+back = "b1"
+tags = ["a", "b"]
+reverse = true
+source = "book"
+
+[[card]]
+front = "f2"
+back = "b2"
+"#;
+
+        let cards = TomlFormat.load(&mut data.as_bytes()).unwrap();
+        let mut output = Vec::new();
+        TomlFormat.write(&cards, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), data);
+    }
+}
@@ -0,0 +1,52 @@
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Instant;
+
+/// Lines typed on stdin for the line based interface.
+///
+/// In timed sessions the lines are read on a thread of their own, so that
+/// waiting for one can give up when a card's time runs out.
+pub struct Lines {
+    receiver: Option<Receiver<std::io::Result<String>>>,
+}
+
+impl Lines {
+    pub fn new(timed: bool) -> Self {
+        let receiver = timed.then(|| {
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                for line in std::io::stdin().lock().lines() {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            receiver
+        });
+
+        Self { receiver }
+    }
+
+    /// The next line, or `None` when `deadline` passes before one is typed.
+    /// Lines read after stdin is closed are empty.
+    pub fn read(&self, deadline: Option<Instant>) -> std::io::Result<Option<String>> {
+        let Some(receiver) = &self.receiver else {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            return Ok(Some(line));
+        };
+
+        let line = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(RecvTimeoutError::from),
+        };
+
+        match line {
+            Ok(line) => line.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Ok(Some(String::new())),
+        }
+    }
+}
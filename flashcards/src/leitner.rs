@@ -0,0 +1,129 @@
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::{CardScheduler, Clock, Grade};
+use crate::Card;
+
+/// Days between reviews for the cards in each box, box 1 first. The number
+/// of entries is the number of boxes.
+pub const DEFAULT_CADENCE: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// Where a card is in the Leitner boxes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Placement {
+    /// Starts at 1.
+    pub number: u32,
+    /// `None` for cards that have never been reviewed, which are always due.
+    pub due: Option<NaiveDate>,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            due: None,
+        }
+    }
+}
+
+/// Schedules cards with Leitner boxes: a correct answer moves a card up a
+/// box, a miss sends it back to the first, and each box is reviewed on its
+/// own cadence.
+pub struct Leitner<C: Clock> {
+    clock: C,
+    cadence: Vec<u32>,
+}
+
+impl<C: Clock> Leitner<C> {
+    /// `cadence` needs at least one box.
+    pub fn new(clock: C, cadence: Vec<u32>) -> Self {
+        assert!(!cadence.is_empty(), "Leitner mode needs at least one box");
+
+        Self { clock, cadence }
+    }
+
+    pub fn boxes(&self) -> u32 {
+        self.cadence.len() as u32
+    }
+}
+
+impl<C: Clock> CardScheduler for Leitner<C> {
+    fn due_cards(&self, cards: &[Card]) -> Vec<usize> {
+        let today = self.clock.today();
+
+        cards
+            .iter()
+            .enumerate()
+            .filter(|(_, card)| card.leitner.due.is_none_or(|due| due <= today))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn grade(&self, card: &mut Card, grade: Grade) {
+        let placement = &mut card.leitner;
+
+        // Cards from sessions with more boxes land in the last box here
+        placement.number = if grade == Grade::Again {
+            1
+        } else {
+            (placement.number + 1).min(self.boxes())
+        };
+
+        let days = self.cadence[placement.number as usize - 1];
+        placement.due = Some(self.clock.today() + Days::new(u64::from(days)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedClock(NaiveDate);
+
+    impl Clock for FixedClock {
+        fn today(&self) -> NaiveDate {
+            self.0
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_grade() {
+        let leitner = Leitner::new(FixedClock(date(10)), vec![1, 3, 7]);
+        let mut card = Card::new("front".to_string(), "back".to_string());
+
+        let cases = vec![
+            (Grade::Good, 2, date(13)),
+            (Grade::Hard, 3, date(17)),
+            (Grade::Easy, 3, date(17)),
+            (Grade::Again, 1, date(11)),
+        ];
+
+        for (index, (grade, number, due)) in cases.into_iter().enumerate() {
+            leitner.grade(&mut card, grade);
+            assert_eq!(
+                card.leitner,
+                Placement {
+                    number,
+                    due: Some(due),
+                },
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_due_cards() {
+        let leitner = Leitner::new(FixedClock(date(10)), DEFAULT_CADENCE.to_vec());
+        let mut cards: Vec<Card> = (0..3)
+            .map(|index| Card::new(format!("front {index}"), "back".to_string()))
+            .collect();
+        cards[1].leitner.due = Some(date(11));
+        cards[2].leitner.due = Some(date(10));
+
+        assert_eq!(leitner.due_cards(&cards), vec![0, 2]);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::anki::FieldMapping;
+use crate::cloze;
+use crate::error::DeckError;
+use crate::format::{self, Format};
+use crate::media;
+use crate::Card;
+
+/// Sides longer than this many characters are reported. They rarely make
+/// good cards, and do not fit on the screen.
+const MAX_SIDE_LENGTH: usize = 500;
+
+/// Something wrong with a deck, found by [`lint`].
+#[derive(Debug)]
+pub enum Finding {
+    /// The deck loads, but has something that is likely a mistake. The
+    /// line is the line of the file or, for formats where lines mean
+    /// little, the position of the card in the deck.
+    Warning { line: u64, message: String },
+    /// The deck does not load at all.
+    Error(DeckError),
+}
+
+impl Finding {
+    fn warning(line: u64, message: String) -> Self {
+        Finding::Warning { line, message }
+    }
+
+    /// The finding as a line of output, in the `path:line: message` form
+    /// editors and pre-commit hooks understand.
+    pub fn render(&self, path: &Path) -> String {
+        match self {
+            Finding::Warning { line, message } => {
+                format!("{}:{line}: {message}", path.display())
+            }
+            Finding::Error(err) => err.to_string(),
+        }
+    }
+}
+
+/// Checks the deck at `path` for errors, and for things that load fine but
+/// are likely mistakes: blank or missing columns, duplicate fronts, empty
+/// or overly long sides and media files that do not exist.
+pub fn lint(path: &Path, format: Option<Format>, mappings: &[FieldMapping]) -> Vec<Finding> {
+    let format = format.unwrap_or_else(|| Format::from_path(path));
+    let mut findings = Vec::new();
+
+    // Anki packages are zip files, so only their cards are checked
+    let mut lines = None;
+    if format != Format::Anki {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(err) => return vec![Finding::Error(DeckError::from(err).with_path(path))],
+        };
+
+        if let Err(err) = std::str::from_utf8(&bytes) {
+            let line = bytes[..err.valid_up_to()]
+                .iter()
+                .filter(|byte| **byte == b'\n')
+                .count()
+                + 1;
+            findings.push(Finding::warning(
+                line as u64,
+                "the text is not valid UTF-8".to_string(),
+            ));
+            return findings;
+        }
+
+        let delimiter = match format {
+            Format::Csv => Some(b','),
+            Format::Tsv => Some(b'\t'),
+            _ => None,
+        };
+        if let Some(delimiter) = delimiter {
+            lines = Some(check_columns(&bytes, delimiter, &mut findings));
+        }
+    }
+
+    match format::load_deck(path, Some(format), mappings) {
+        Ok(cards) => {
+            let line = |index: usize| {
+                lines
+                    .as_ref()
+                    .and_then(|lines: &Vec<u64>| lines.get(index).copied())
+                    .unwrap_or(index as u64 + 1)
+            };
+            let dir = path.parent().unwrap_or(Path::new(""));
+            findings.extend(check_cards(&cards, line, dir));
+        }
+        Err(err) => findings.push(Finding::Error(err)),
+    }
+
+    findings
+}
+
+/// Reports header columns without a name, usually left by a trailing
+/// delimiter, and rows whose column count differs from the header's.
+/// Returns the line each row after the header starts on.
+fn check_columns(bytes: &[u8], delimiter: u8, findings: &mut Vec<Finding>) -> Vec<u64> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(bytes);
+
+    let mut lines = Vec::new();
+    let mut columns = None;
+    // Malformed rows are left for loading the deck to report
+    for record in reader.byte_records().map_while(Result::ok) {
+        let line = record.position().map_or(0, |pos| pos.line());
+
+        let Some(columns) = columns else {
+            for (index, name) in record.iter().enumerate() {
+                if name.trim_ascii().is_empty() {
+                    let cause = if index + 1 == record.len() {
+                        ", probably from a trailing delimiter"
+                    } else {
+                        ""
+                    };
+                    findings.push(Finding::warning(
+                        line,
+                        format!("column {} has no name{cause}", index + 1),
+                    ));
+                }
+            }
+            columns = Some(record.len());
+            continue;
+        };
+
+        if record.len() != columns {
+            findings.push(Finding::warning(
+                line,
+                format!(
+                    "row has {} columns, but the header has {columns}",
+                    record.len()
+                ),
+            ));
+        }
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Reports duplicate fronts, empty and overly long sides, and media files
+/// missing from `dir`. `line` gives the line of the card at an index.
+fn check_cards(cards: &[Card], line: impl Fn(usize) -> u64, dir: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut fronts = HashMap::new();
+
+    for (index, card) in cards.iter().enumerate() {
+        let line = line(index);
+        let mut warn = |message| findings.push(Finding::warning(line, message));
+
+        // Cloze rows keep their answers on the front, so need no back
+        let cloze = cloze::has_deletions(card.front());
+        for (side, text) in [("front", card.front()), ("back", card.back())] {
+            let length = text.chars().count();
+            if length == 0 && !(cloze && side == "back") {
+                warn(format!("the {side} is empty"));
+            } else if length > MAX_SIDE_LENGTH {
+                warn(format!(
+                    "the {side} is {length} characters long (more than {MAX_SIDE_LENGTH})"
+                ));
+            }
+        }
+
+        let front = card.front().trim().to_lowercase();
+        if !front.is_empty() {
+            if let Some(first_line) = fronts.get(&front) {
+                warn(format!(
+                    "duplicate front \"{}\" (first seen on line {first_line})",
+                    card.front()
+                ));
+            } else {
+                fronts.insert(front, line);
+            }
+        }
+
+        for text in [card.front(), card.back(), &card.hint, &card.notes] {
+            for reference in media::references(text) {
+                if !dir.join(reference).is_file() {
+                    warn(format!("media file \"{reference}\" does not exist"));
+                }
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(findings: &[Finding]) -> Vec<String> {
+        findings
+            .iter()
+            .map(|finding| finding.render(Path::new("deck.csv")))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_columns() {
+        let cases = vec![
+            ("front,back\na,1\nb,2\n", vec![]),
+            (
+                "front, back,\nfront_1, back_1,\n",
+                vec!["deck.csv:1: column 3 has no name, probably from a trailing delimiter"],
+            ),
+            (
+                "front,,back\na,,1\n",
+                vec!["deck.csv:1: column 2 has no name"],
+            ),
+            (
+                "front,back\na,1\nb,2,extra\nc\n",
+                vec![
+                    "deck.csv:3: row has 3 columns, but the header has 2",
+                    "deck.csv:4: row has 1 columns, but the header has 2",
+                ],
+            ),
+        ];
+
+        for (index, (data, expected)) in cases.into_iter().enumerate() {
+            let mut findings = Vec::new();
+            check_columns(data.as_bytes(), b',', &mut findings);
+            assert_eq!(messages(&findings), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_check_cards() {
+        let card = |front: &str, back: &str| Card::new(front.to_string(), back.to_string());
+        let cards = vec![
+            card("What is Rust?", "A language"),
+            card("what is rust? ", "A systems language"),
+            card("", "no front"),
+            card("The {{c1::Ord}} trait", ""),
+            card("no back", ""),
+            card("long", &"x".repeat(MAX_SIDE_LENGTH + 1)),
+            card("[img:missing.png]", "[sound:Cargo.toml]"),
+        ];
+
+        let findings = check_cards(&cards, |index| index as u64 + 2, Path::new("."));
+
+        assert_eq!(
+            messages(&findings),
+            vec![
+                "deck.csv:3: duplicate front \"what is rust? \" (first seen on line 2)",
+                "deck.csv:4: the front is empty",
+                "deck.csv:6: the back is empty",
+                "deck.csv:7: the back is 501 characters long (more than 500)",
+                "deck.csv:8: media file \"missing.png\" does not exist",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_invalid_utf8() {
+        let path = std::env::temp_dir().join(format!("flashcards-{}-lint.csv", std::process::id()));
+        std::fs::write(&path, b"front,back\na,1\nb,\xff\n").unwrap();
+
+        let findings = lint(&path, None, &[]);
+
+        assert_eq!(
+            messages(&findings),
+            vec!["deck.csv:3: the text is not valid UTF-8"]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
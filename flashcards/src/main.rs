@@ -1,15 +1,116 @@
-use std::fs::File;
+use std::collections::HashSet;
+use std::io::{BufReader, IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
 
-use rand::seq::SliceRandom;
+use clap::Parser;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
+mod anki;
+mod choice;
+mod cli;
+mod cloze;
+mod config;
+mod deck;
+mod direction;
+mod error;
+mod export;
+mod format;
+mod input;
+mod leitner;
+mod lint;
+mod markdown;
+mod media;
+mod order;
+mod progress;
+mod quiz;
+mod scheduler;
+mod script;
+mod serve;
+mod session;
+mod stats;
+mod tui;
+
+use cli::{
+    Cli, CliCommand, DeckArgs, ExportArgs, ImportArgs, NewArgs, ServeArgs, SessionArgs, StatsArgs,
+};
+use config::{Config, Settings};
+use deck::{Deck, Filter};
+use direction::Direction;
+use error::{DeckError, Error};
+use leitner::{Leitner, Placement};
+use order::{parse_order, Order};
+use progress::{Review, Stats};
+use scheduler::{CardScheduler, Clock, Grade, Schedule, Scheduler, SystemClock};
+use session::{Event, Frontend, Session, View};
+
+#[derive(Clone)]
 struct Card {
     front: String,
     back: String,
+    /// Identity from the deck's `id` column, if it has one.
+    id: Option<String>,
+    tags: Vec<String>,
+    hint: String,
+    notes: String,
+    reverse: bool,
+    /// Set on the turned-around copy of a card, which shows the back first.
+    reversed: bool,
+    /// The deletion group hidden on the front of cards made from a cloze row.
+    cloze: Option<u32>,
+    /// Suspended cards stay in the deck but are left out of sessions.
+    suspended: bool,
+    flagged: bool,
+    /// Columns the schema does not know about, kept in file order so that
+    /// writing the deck back out does not lose them.
+    extra: Vec<(String, String)>,
+    /// Names of the fields the card was read from, in file order, each with
+    /// the header the file gives it, so that writing the deck back keeps its
+    /// columns where they were and as they were named.
+    fields: Vec<(String, String)>,
+    schedule: Schedule,
+    /// The card's box for Leitner sessions, kept apart from its SM-2 schedule.
+    leitner: Placement,
+    stats: Stats,
+    /// Index of the deck the card was loaded from, among the session's decks.
+    deck: usize,
+    /// Position of the card's row among the rows of its deck file.
+    row: usize,
 }
 
 impl Card {
     fn new(front: String, back: String) -> Self {
-        Self { front, back }
+        Self {
+            front,
+            back,
+            id: None,
+            tags: Vec::new(),
+            hint: String::new(),
+            notes: String::new(),
+            reverse: false,
+            reversed: false,
+            cloze: None,
+            suspended: false,
+            flagged: false,
+            extra: Vec::new(),
+            fields: Vec::new(),
+            schedule: Schedule::default(),
+            leitner: Placement::default(),
+            stats: Stats::default(),
+            deck: 0,
+            row: 0,
+        }
+    }
+
+    /// Stable identity used to remember progress across sessions. Decks with
+    /// an `id` column keep their progress even when a card's text is edited.
+    fn id(&self) -> String {
+        match &self.id {
+            Some(id) => id.clone(),
+            None => progress::card_id(self.front(), self.back()),
+        }
     }
 
     fn front(&self) -> &str {
@@ -21,116 +122,750 @@ impl Card {
     }
 }
 
-fn ui(state: &str, text: &str, count: usize, total: usize) -> String {
+/// The text shown for the side of the card that is facing up, along with
+/// the card's hint, notes, tags and extra columns where they apply.
+fn card_text(card: &Card, state: &str, show_hint: bool) -> String {
+    let mut text = if state == "back" {
+        card.back().to_string()
+    } else {
+        card.front().to_string()
+    };
+
+    if state != "back" && show_hint {
+        if card.hint.is_empty() {
+            text.push_str("\n\nhint: this card has no hint");
+        } else {
+            text.push_str(&format!("\n\nhint: {}", card.hint));
+        }
+    }
+
+    if state == "back" {
+        if !card.notes.is_empty() {
+            text.push_str(&format!("\n\nnotes: {}", card.notes));
+        }
+        for (name, value) in &card.extra {
+            if !value.is_empty() {
+                text.push_str(&format!("\n{name}: {value}"));
+            }
+        }
+    }
+
+    if !card.tags.is_empty() {
+        text.push_str(&format!("\n\ntags: {}", card.tags.join(" ")));
+    }
+
+    text
+}
+
+/// What the header says about the card being shown: its deck, whether it
+/// is turned around, and its box when studying with `boxes` Leitner boxes.
+fn card_label(deck: &str, card: &Card, boxes: Option<u32>) -> String {
+    // Turned-around cards say so, since their front is the deck's back
+    let mut label = if card.reversed {
+        format!("{deck} (reversed)")
+    } else {
+        deck.to_string()
+    };
+
+    if let Some(boxes) = boxes {
+        label.push_str(&format!(", box {} of {boxes}", card.leitner.number));
+    }
+    if card.flagged {
+        label.push_str(", flagged");
+    }
+
+    label
+}
+
+fn ui(deck: &str, state: &str, text: &str, count: usize, total: usize) -> String {
+    let grades = if state == "back" {
+        "grade: (1) again (2) hard (3) good (4) easy\n\n"
+    } else {
+        ""
+    };
+
+    format!(
+        "deck: {deck}
+
+{state}: {text}
+
+{count}/{total}
+
+(p)revious (n)ext (f)lip (h)int (s)huffle (o)rder (q)uit
+edit: ef <front>, eb <back>, add <front> | <back>, delete, suspend, flag
+
+{grades}"
+    )
+}
+
+fn quiz_ui(deck: &str, state: &str, text: &str, count: usize, total: usize) -> String {
+    format!(
+        "deck: {deck}
+
+{state}: {text}
+
+{count}/{total}
+
+type your answer, or :p :n :f :h :s :o :q
+
+"
+    )
+}
+
+fn choice_ui(deck: &str, text: &str, options: &[&str], count: usize, total: usize) -> String {
+    let options: String = options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| format!("{}) {option}\n", index + 1))
+        .collect();
+
     format!(
-        "{state}: {text}
+        "deck: {deck}
 
+front: {text}
+
+{options}
 {count}/{total}
 
-(p)revious (n)ext (f)lip (s)huffle (q)uit
+pick an answer by number, or :p :n :h :s :o :q
 
 "
     )
 }
 
+/// The line based screen for a view: the feedback on the last command and
+/// the card, showing `text` for the card's text, laid out for the session's
+/// mode.
+fn screen(view: &View, text: &str) -> String {
+    let screen = match view.mode {
+        Mode::Study => ui(&view.deck, view.state, text, view.count, view.total),
+        Mode::Quiz => quiz_ui(&view.deck, view.state, text, view.count, view.total),
+        Mode::Choice => choice_ui(&view.deck, text, &view.backs, view.count, view.total),
+    };
+
+    format!("{}{screen}\n", view.feedback)
+}
+
+/// How the user answers the cards in a session.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    /// Flip the card and grade your own recall.
+    Study,
+    /// Type the answer and have it checked.
+    Quiz,
+    /// Pick the answer out of several backs from the deck.
+    Choice,
+}
+
+fn parse_mode(input: &str) -> Option<Mode> {
+    match input {
+        "study" => Some(Mode::Study),
+        "quiz" => Some(Mode::Quiz),
+        "choice" => Some(Mode::Choice),
+        _ => None,
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum Command {
     Previous,
     Next,
     Flip,
+    Hint,
     Quit,
     Shuffle,
+    /// Switches to the given order, or the next one when none is given.
+    Order(Option<Order>),
+    Grade(Grade),
+    /// Replaces the text of the front, as shown, of the current card.
+    EditFront(String),
+    EditBack(String),
+    /// Adds a card with this front and back to the current card's deck.
+    Add(String, String),
+    Delete,
+    Suspend,
+    /// Flags the current card, or unflags it if it already is.
+    Flag,
+    Answer(String),
+    Choose(usize),
+    /// The time for answering the card ran out.
+    Timeout,
     Unknown,
 }
 
 fn parse_command(input: &str) -> Command {
-    match input.to_lowercase().as_str() {
+    if let Some(command) = parse_edit(input) {
+        return command;
+    }
+
+    let input = input.to_lowercase();
+    if let Some(name) = input
+        .strip_prefix("o ")
+        .or_else(|| input.strip_prefix("order "))
+    {
+        return parse_order(name.trim())
+            .map_or(Command::Unknown, |order| Command::Order(Some(order)));
+    }
+
+    match input.as_str() {
         "n" | "next" => Command::Next,
         "p" | "previous" => Command::Previous,
         "f" | "flip" => Command::Flip,
+        "h" | "hint" => Command::Hint,
         "q" | "quit" => Command::Quit,
         "s" | "shuffle" => Command::Shuffle,
+        "o" | "order" => Command::Order(None),
+        "1" | "again" => Command::Grade(Grade::Again),
+        "2" | "hard" => Command::Grade(Grade::Hard),
+        "3" | "good" => Command::Grade(Grade::Good),
+        "4" | "easy" => Command::Grade(Grade::Easy),
         _ => Command::Unknown,
     }
 }
 
-fn load_from_csv(reader: impl std::io::Read) -> Result<Vec<Card>, std::io::Error> {
-    let mut rdr = csv::Reader::from_reader(reader);
+/// Parses the editing commands, which keep the case of the text they take.
+fn parse_edit(input: &str) -> Option<Command> {
+    let (name, text) = input.split_once(' ').unwrap_or((input, ""));
+    let mut name = name.to_lowercase();
+    let mut text = text.trim();
 
-    let mut cards = Vec::new();
-    for result in rdr.records() {
-        let record = result?;
+    // `edit front` and `edit back` are the long forms of `ef` and `eb`
+    if name == "edit" {
+        let (side, rest) = text.split_once(' ').unwrap_or((text, ""));
+        name = match side.to_lowercase().as_str() {
+            "front" => "ef".to_string(),
+            "back" => "eb".to_string(),
+            _ => return None,
+        };
+        text = rest.trim();
+    }
 
-        cards.push(Card::new(
-            record[0].to_string().trim().to_string(),
-            record[1].to_string().trim().to_string(),
-        ));
+    match (name.as_str(), text.is_empty()) {
+        ("ef", false) => Some(Command::EditFront(text.to_string())),
+        ("eb", false) => Some(Command::EditBack(text.to_string())),
+        ("add", false) => {
+            let (front, back) = text.split_once('|')?;
+            let front = front.trim();
+            (!front.is_empty()).then(|| Command::Add(front.to_string(), back.trim().to_string()))
+        }
+        ("delete", true) => Some(Command::Delete),
+        ("suspend", true) => Some(Command::Suspend),
+        ("flag", true) => Some(Command::Flag),
+        _ => None,
+    }
+}
+
+/// Parses a line of input for the given mode. The answer modes take any
+/// text as an answer, so their commands are prefixed with a `:`.
+fn parse_input(input: &str, mode: Mode, options: &[usize]) -> Command {
+    if mode == Mode::Study {
+        parse_command(input)
+    } else if let Some(input) = input.strip_prefix(':') {
+        parse_command(input)
+    } else if input.is_empty() {
+        Command::Unknown
+    } else if mode == Mode::Choice {
+        choice::pick(options, input).map_or(Command::Unknown, Command::Choose)
+    } else {
+        Command::Answer(input.to_string())
     }
+}
+
+/// The line based front-end: each view is printed on a cleared screen and
+/// commands are read a line at a time from stdin.
+struct LineFrontend {
+    lines: input::Lines,
+    /// Whether card text is rendered as Markdown.
+    markdown: bool,
+    images: media::Images,
+    mode: Mode,
+    options: Vec<usize>,
+}
 
-    Ok(cards)
+impl LineFrontend {
+    fn new(timed: bool, markdown: bool, graphics: media::Graphics) -> Self {
+        Self {
+            lines: input::Lines::new(timed),
+            markdown,
+            images: media::Images::new(graphics),
+            mode: Mode::Study,
+            options: Vec::new(),
+        }
+    }
 }
 
-fn main() -> std::io::Result<()> {
-    let mut args = std::env::args().skip(1);
+impl Frontend for LineFrontend {
+    fn show(&mut self, view: &View) -> std::io::Result<()> {
+        let _ = clearscreen::clear();
+
+        let mut text = if self.markdown {
+            // The first line follows the `front: ` label
+            let (width, _) = crossterm::terminal::size()
+                .ok()
+                .filter(|(width, _)| *width > 0)
+                .unwrap_or((80, 24));
+            let width = usize::from(width).saturating_sub(view.state.len() + 2);
+            let lines = markdown::render(&view.text, width);
+            lines
+                .into_iter()
+                .map(|line| line.text)
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            view.text.clone()
+        };
+        for path in view.images() {
+            if let Some(image) = self.images.draw(path) {
+                text.push('\n');
+                text.push_str(image);
+            }
+        }
+        print!("{}{}", self.images.clear(), screen(view, &text));
+        if let Some(deadline) = view.deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            println!("{}s to answer\n", tui::seconds_left(left));
+        }
 
-    let file_path = args.next().expect("Must pass the path to the csv file");
+        // Choices are typed as option numbers, so the next read needs them
+        self.mode = view.mode;
+        self.options = view.options.to_vec();
+        Ok(())
+    }
 
-    let file_reader = File::open(file_path).expect("Unable to open file");
+    fn read(&mut self, deadline: Option<Instant>) -> std::io::Result<Command> {
+        Ok(match self.lines.read(deadline)? {
+            // Only a closed stdin gives a line without its newline
+            Some(line) if line.is_empty() => Command::Quit,
+            Some(line) => parse_input(line.trim(), self.mode, &self.options),
+            None => Command::Timeout,
+        })
+    }
+}
 
-    let mut cards = load_from_csv(file_reader).expect("Unable to load csv file");
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            // clap reports --help and --version this way too
+            let _ = err.print();
+            return if err.use_stderr() {
+                ExitCode::from(error::EXIT_USAGE)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
 
-    // Clearing the screen
-    let _ = clearscreen::clear();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
 
-    // Set initial state for application
-    let mut command = Command::Unknown;
-    let mut current_index = 0;
-    let mut state = "front";
-    let mut text = cards[current_index].front();
+fn run(cli: Cli) -> Result<(), Error> {
+    match cli.command {
+        CliCommand::Study(args) => {
+            let config = Config::load(cli.config.as_deref())?;
+            study(&args, &config.settings(&args, None))
+        }
+        CliCommand::Quiz(args) => {
+            let config = Config::load(cli.config.as_deref())?;
+            study(&args, &config.settings(&args, Some(Mode::Quiz)))
+        }
+        CliCommand::Serve(args) => {
+            let config = Config::load(cli.config.as_deref())?;
+            serve(&args, &config.settings(&args.session, None))
+        }
+        CliCommand::Stats(args) => show_stats(&args),
+        CliCommand::Import(args) => import(&args),
+        CliCommand::Export(args) => export(&args),
+        CliCommand::Validate(args) => validate(&args),
+        CliCommand::New(args) => new_deck(&args),
+    }
+}
 
-    while command != Command::Quit {
-        println!("{}", ui(state, text, current_index + 1, cards.len()));
-        let mut user_input = String::new();
-        std::io::stdin().read_line(&mut user_input)?;
+/// Loads every deck named by `args`, and the cards that pass the filters.
+/// Suspended cards are among them, since only sessions leave them out.
+fn load_decks(args: &DeckArgs) -> Result<(Vec<Deck>, Vec<Card>), Error> {
+    let filter = Filter {
+        decks: args.decks.clone(),
+        tags: args.tags.clone(),
+    };
 
-        command = parse_command(user_input.trim());
+    // Every deck keeps its own progress file, so cards remember where they
+    // came from
+    let mut decks = Vec::new();
+    let mut cards = Vec::new();
+    for path in deck::deck_paths(&args.paths)? {
+        let mut deck = Deck::new(path)?;
+        let mut deck_cards = format::load_deck(&deck.path, args.format, &args.mappings)?;
+        for (row, card) in deck_cards.iter_mut().enumerate() {
+            card.deck = decks.len();
+            card.row = row;
+        }
+        // Media files are looked for next to the deck. Missing ones, such
+        // as the media of Anki packages, are shown as their reference
+        let dir = deck.path.parent().unwrap_or(Path::new(""));
+        let missing = deck_cards
+            .iter()
+            .filter(|card| media::is_missing_media(card, dir))
+            .count();
+        if missing > 0 {
+            eprintln!(
+                "warning: {}: {missing} cards refer to media files that do not exist; \
+                 `flashcards validate` lists them",
+                deck.path.display()
+            );
+        }
+        deck.format = args.format;
+        deck.rows = deck_cards.iter().cloned().map(Some).collect();
 
-        match command {
-            Command::Next => {
-                if current_index + 1 < cards.len() {
-                    current_index += 1;
-                    state = "front";
-                    text = cards[current_index].front();
-                }
-            }
-            Command::Previous => {
-                if current_index > 0 {
-                    current_index -= 1;
-                    state = "front";
-                    text = cards[current_index].front();
-                }
+        // Cloze rows become a card for each of their deletions
+        for card in deck_cards.into_iter().flat_map(cloze::expand) {
+            if filter.matches(&card, &deck) {
+                cards.push(card);
             }
-            Command::Flip => {
-                if state.eq("back") {
-                    state = "front";
-                    text = cards[current_index].front();
-                } else {
-                    state = "back";
-                    text = cards[current_index].back();
-                }
-            }
-            Command::Shuffle => {
-                let mut rng = rand::thread_rng();
-                cards.shuffle(&mut rng);
-                current_index = 0;
-                state = "front";
-                text = cards[current_index].front();
-            }
-            Command::Quit | Command::Unknown => {}
         }
+        decks.push(deck);
+    }
+    if decks.is_empty() {
+        return Err(Error::Usage("no decks were found".to_string()));
+    }
 
-        // Clearing the screen
-        let _ = clearscreen::clear();
+    Ok((decks, cards))
+}
+
+/// Turns the cards to face `direction` and copies their progress over. The
+/// progress has to come last, since turning a card changes its id.
+fn arrange_cards(
+    decks: &[Deck],
+    cards: Vec<Card>,
+    direction: Direction,
+    rng: &mut impl rand::Rng,
+) -> Vec<Card> {
+    let mut cards = direction::arrange(cards, direction, rng);
+    for card in &mut cards {
+        decks[card.deck].store.apply(std::slice::from_mut(card));
+    }
+
+    cards
+}
+
+fn show_stats(args: &StatsArgs) -> Result<(), Error> {
+    let (decks, cards) = load_decks(&args.decks)?;
+    let today = SystemClock.today();
+
+    let report = stats_report(&decks, cards, today);
+    if args.json {
+        let json = serde_json::to_string_pretty(&report).map_err(std::io::Error::from)?;
+        println!("{json}");
+    } else {
+        print!("{}", report.render(today));
+    }
+
+    Ok(())
+}
+
+/// The stats of `cards`, counting them both ways round.
+fn stats_report(decks: &[Deck], cards: Vec<Card>, today: chrono::NaiveDate) -> stats::Report {
+    // Cards studied back to front have progress of their own. Those turned
+    // around only for the stats, and never seen that way, are left out
+    let cards: Vec<Card> = arrange_cards(decks, cards, Direction::Both, &mut rand::thread_rng())
+        .into_iter()
+        .filter(|card| !card.reversed || card.reverse || card.stats.last_seen.is_some())
+        .collect();
+
+    let rows = decks
+        .iter()
+        .enumerate()
+        .map(|(index, deck)| stats::DeckRow {
+            name: deck.name.clone(),
+            stats: stats::DeckStats::new(cards.iter().filter(|card| card.deck == index), today),
+        })
+        .filter(|row| row.stats.cards > 0)
+        .collect();
+    let named_cards: Vec<(&str, &Card)> = cards
+        .iter()
+        .map(|card| (decks[card.deck].name.as_str(), card))
+        .collect();
+
+    // Reviews of cards that were filtered out do not count
+    let ids: HashSet<String> = cards.iter().map(Card::id).collect();
+    let mut reviews: Vec<Review> = decks
+        .iter()
+        .flat_map(|deck| deck.store.reviews())
+        .filter(|review| ids.contains(&review.card))
+        .cloned()
+        .collect();
+    reviews.sort_by_key(|review| review.at);
+
+    stats::Report::new(rows, &named_cards, &reviews, today)
+}
+
+/// Adds the cards of another deck that are not in `args.deck` yet, creating
+/// it when needed.
+fn import(args: &ImportArgs) -> Result<(), Error> {
+    let imported = format::load_deck(&args.source, args.format, &args.mappings)?;
+
+    let mut cards = if args.deck.exists() {
+        format::load_deck(&args.deck, None, &[])?
+    } else {
+        Vec::new()
+    };
+
+    let known: HashSet<String> = cards.iter().map(Card::id).collect();
+    let total = imported.len();
+    cards.extend(
+        imported
+            .into_iter()
+            .filter(|card| !known.contains(&card.id())),
+    );
+    let added = cards.len() - known.len();
+
+    format::write_deck(&args.deck, None, &cards)?;
+    println!(
+        "Imported {added} cards into {} ({} already there)",
+        args.deck.display(),
+        total - added
+    );
+
+    Ok(())
+}
+
+/// Writes the filtered cards of all the decks to one file, in the format
+/// its extension names.
+fn export(args: &ExportArgs) -> Result<(), Error> {
+    let (decks, cards) = load_decks(&args.decks)?;
+
+    let Some(to) = args.to else {
+        let rows = export::rows(&decks, &cards);
+
+        // clap makes sure there is an output when there is no `--to`
+        let output = args.output.as_deref().unwrap_or(Path::new(""));
+        format::write_deck(output, None, &rows)?;
+        println!("Wrote {} cards to {}", rows.len(), output.display());
+        return Ok(());
+    };
+
+    // Exports carry the scheduling, so they need the cards' progress
+    let cards = arrange_cards(
+        &decks,
+        cards,
+        Direction::FrontToBack,
+        &mut rand::thread_rng(),
+    );
+    let pairs: Vec<(&str, &Card)> = cards
+        .iter()
+        .map(|card| (decks[card.deck].name.as_str(), card))
+        .collect();
+
+    match &args.output {
+        Some(output) => {
+            let file = std::fs::File::create(output)
+                .map_err(|err| DeckError::from(err).with_path(output))?;
+            let mut writer = std::io::BufWriter::new(file);
+            to.write(&pairs, &mut writer)
+                .and_then(|()| Ok(writer.flush()?))
+                .map_err(|err| err.with_path(output))?;
+            println!("Wrote {} cards to {}", cards.len(), output.display());
+        }
+        None => to.write(&pairs, &mut std::io::stdout().lock())?,
+    }
+
+    Ok(())
+}
+
+/// Lists the problems found in the decks, one per line, and fails if there
+/// are any, so it can run as a pre-commit hook.
+fn validate(args: &DeckArgs) -> Result<(), Error> {
+    let mut count = 0;
+    for path in deck::deck_paths(&args.paths)? {
+        let findings = lint::lint(&path, args.format, &args.mappings);
+        for finding in &findings {
+            println!("{}", finding.render(&path));
+        }
+        count += findings.len();
+    }
+
+    if count > 0 {
+        return Err(Error::Findings(count));
+    }
+    println!("No problems found.");
+
+    Ok(())
+}
+
+fn new_deck(args: &NewArgs) -> Result<(), Error> {
+    if args.path.exists() {
+        return Err(Error::Usage(format!(
+            "{} already exists",
+            args.path.display()
+        )));
+    }
+
+    let mut card = Card::new(
+        "What goes on the front of a card?".to_string(),
+        "The question; the answer goes on the back.".to_string(),
+    );
+    card.tags = vec!["example".to_string()];
+
+    format::write_deck(&args.path, None, &[card])?;
+    println!("Created {}", args.path.display());
+
+    Ok(())
+}
+
+/// Loads the decks and queues their due cards for a session, in the order
+/// and up to the limit of `settings`. Returns `None`, having said why, when
+/// there is nothing to study.
+fn start_session(args: &DeckArgs, settings: &Settings) -> Result<Option<Session>, Error> {
+    let (decks, mut cards) = load_decks(args)?;
+    // Suspended cards stay in the deck, but are not studied
+    cards.retain(|card| !card.suspended);
+    if cards.is_empty() {
+        println!("No cards match the filters.");
+        return Ok(None);
+    }
+
+    // A seed makes the shuffles, directions and choice options repeatable
+    let mut rng = match settings.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let cards = arrange_cards(&decks, cards, settings.direction, &mut rng);
+
+    let scheduler: Box<dyn CardScheduler> = match &settings.leitner {
+        Some(cadence) => Box::new(Leitner::new(SystemClock, cadence.clone())),
+        None => Box::new(Scheduler::new(SystemClock)),
+    };
+
+    // Only the cards that are due today are studied
+    let mut queue = scheduler.due_cards(&cards);
+    if queue.is_empty() {
+        println!("No cards are due today.");
+        return Ok(None);
+    }
+    settings.order.apply(&cards, &mut queue, &mut rng);
+    if let Some(limit) = settings.limit {
+        queue.truncate(limit);
+    }
+
+    Ok(Some(Session::new(
+        settings, decks, cards, queue, scheduler, rng,
+    )))
+}
+
+fn study(args: &SessionArgs, settings: &Settings) -> Result<(), Error> {
+    let mode = settings.mode;
+    let Some(mut session) = start_session(&args.decks, settings)? else {
+        return Ok(());
+    };
+
+    // Scripts take their commands from a file or stdin and print every
+    // screen. Otherwise the full-screen UI is used for study sessions on a
+    // terminal; anything piped in or out, and the typed answer modes, stay
+    // line based
+    let scripted = args.script.is_some() || args.batch;
+    // Markdown is only rendered for a terminal, so piped output stays as
+    // the decks have it
+    let terminal = std::io::stdout().is_terminal();
+    let markdown = !settings.plain && terminal;
+    let graphics = if terminal {
+        settings.images
+    } else {
+        media::Graphics::Text
+    };
+    let mut frontend: Box<dyn Frontend> = if let Some(path) = &args.script {
+        let file = std::fs::File::open(path).map_err(|err| DeckError::from(err).with_path(path))?;
+        let out = std::io::stdout();
+        Box::new(script::Script::new(BufReader::new(file), out, args.json))
+    } else if args.batch {
+        let (stdin, out) = (std::io::stdin().lock(), std::io::stdout());
+        Box::new(script::Script::new(stdin, out, args.json))
+    } else if mode == Mode::Study
+        && !settings.line
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal()
+    {
+        Box::new(tui::Terminal::enter(markdown, graphics)?)
+    } else {
+        Box::new(LineFrontend::new(
+            settings.timed.is_some(),
+            markdown,
+            graphics,
+        ))
+    };
+    let mut player = settings.audio_command.as_deref().map(media::Player::new);
+
+    loop {
+        frontend.show(&session.view())?;
+        let command = frontend.read(session.deadline())?;
+        let event = session.apply(command)?;
+        frontend.event(&event)?;
+        if event == Event::Quit || session.is_finished() {
+            break;
+        }
+
+        // Sounds play as their side of the card turns up. One that does not
+        // play is no reason to stop studying
+        if let (Some(player), Event::Flipped) = (player.as_mut(), event) {
+            let _ = player.play(&session.view().sounds());
+        }
+    }
+
+    // Leave the full-screen UI before printing the summary
+    drop(frontend);
+    session.save()?;
+
+    if args.json {
+        let summary = serde_json::json!({
+            "type": "summary",
+            "finished": session.is_finished(),
+            "score": (mode != Mode::Study).then(|| session.score()),
+        });
+        println!("{summary}");
+        return Ok(());
+    }
+    if session.is_finished() {
+        if !scripted {
+            let _ = clearscreen::clear();
+        }
+        print!("{}", session.feedback());
+        println!("All due cards have been reviewed.");
+    }
+    if mode != Mode::Study {
+        println!("{}", session.score().summary());
+    }
+
+    Ok(())
+}
+
+fn serve(args: &ServeArgs, settings: &Settings) -> Result<(), Error> {
+    if args.session.script.is_some() || args.session.batch {
+        return Err(Error::Usage(
+            "--script and --batch cannot be used with serve".to_string(),
+        ));
+    }
+    let Some(session) = start_session(&args.session.decks, settings)? else {
+        return Ok(());
+    };
+
+    let server = serve::Server::bind((args.host.as_str(), args.port), session)?;
+    println!(
+        "Studying on http://{}/, press Ctrl-C or enter q on the page to stop.",
+        server.addr()
+    );
+    let session = server.run()?;
+
+    if session.is_finished() {
+        println!("All due cards have been reviewed.");
+    }
+    if settings.mode != Mode::Study {
+        println!("{}", session.score().summary());
     }
 
     Ok(())
@@ -153,19 +888,124 @@ mod tests {
 
     #[test]
     fn test_ui() {
-        let expected_ui = "front: front of card
+        let expected_ui = "deck: rust
+
+front: front of card
+
+1/10
+
+(p)revious (n)ext (f)lip (h)int (s)huffle (o)rder (q)uit
+edit: ef <front>, eb <back>, add <front> | <back>, delete, suspend, flag
+
+";
+
+        let card_ui = ui("rust", "front", "front of card", 1, 10);
+
+        assert_eq!(card_ui, expected_ui);
+    }
+
+    #[test]
+    fn test_ui_back() {
+        let expected_ui = "deck: rust
+
+back: back of card
 
 1/10
 
-(p)revious (n)ext (f)lip (s)huffle (q)uit
+(p)revious (n)ext (f)lip (h)int (s)huffle (o)rder (q)uit
+edit: ef <front>, eb <back>, add <front> | <back>, delete, suspend, flag
+
+grade: (1) again (2) hard (3) good (4) easy
 
 ";
 
-        let card_ui = ui("front", "front of card", 1, 10);
+        let card_ui = ui("rust", "back", "back of card", 1, 10);
 
         assert_eq!(card_ui, expected_ui);
     }
 
+    #[test]
+    fn test_quiz_ui() {
+        let expected_ui = "deck: rust
+
+front: front of card
+
+1/10
+
+type your answer, or :p :n :f :h :s :o :q
+
+";
+
+        let card_ui = quiz_ui("rust", "front", "front of card", 1, 10);
+
+        assert_eq!(card_ui, expected_ui);
+    }
+
+    #[test]
+    fn test_choice_ui() {
+        let expected_ui = "deck: rust
+
+front: front of card
+
+1) one
+2) two
+3) three
+4) four
+
+1/10
+
+pick an answer by number, or :p :n :h :s :o :q
+
+";
+
+        let card_ui = choice_ui(
+            "rust",
+            "front of card",
+            &["one", "two", "three", "four"],
+            1,
+            10,
+        );
+
+        assert_eq!(card_ui, expected_ui);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        let cases = vec![
+            ("study", Some(Mode::Study)),
+            ("quiz", Some(Mode::Quiz)),
+            ("choice", Some(Mode::Choice)),
+            ("unknown", None),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(parse_mode(input), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let options = vec![4, 2, 7, 0];
+        let cases = vec![
+            ("n", Mode::Study, Command::Next),
+            ("3", Mode::Study, Command::Grade(Grade::Good)),
+            ("n", Mode::Quiz, Command::Answer("n".to_string())),
+            (":n", Mode::Quiz, Command::Next),
+            ("", Mode::Quiz, Command::Unknown),
+            ("2", Mode::Choice, Command::Choose(2)),
+            ("5", Mode::Choice, Command::Unknown),
+            (":q", Mode::Choice, Command::Quit),
+        ];
+
+        for (index, (input, mode, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                parse_input(input, mode, &options),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
     #[test]
     fn test_parse_command() {
         let cases = vec![
@@ -175,10 +1015,42 @@ mod tests {
             ("previous", Command::Previous),
             ("f", Command::Flip),
             ("flip", Command::Flip),
+            ("h", Command::Hint),
+            ("hint", Command::Hint),
             ("q", Command::Quit),
             ("quit", Command::Quit),
             ("s", Command::Shuffle),
             ("shuffle", Command::Shuffle),
+            ("o", Command::Order(None)),
+            ("order", Command::Order(None)),
+            ("o weakest", Command::Order(Some(Order::Weakest))),
+            ("Order Oldest", Command::Order(Some(Order::Oldest))),
+            ("o sideways", Command::Unknown),
+            ("1", Command::Grade(Grade::Again)),
+            ("again", Command::Grade(Grade::Again)),
+            ("2", Command::Grade(Grade::Hard)),
+            ("hard", Command::Grade(Grade::Hard)),
+            ("3", Command::Grade(Grade::Good)),
+            ("good", Command::Grade(Grade::Good)),
+            ("4", Command::Grade(Grade::Easy)),
+            ("easy", Command::Grade(Grade::Easy)),
+            (
+                "ef Fixed Front",
+                Command::EditFront("Fixed Front".to_string()),
+            ),
+            (
+                "edit back  New Back",
+                Command::EditBack("New Back".to_string()),
+            ),
+            (
+                "add Front | Back",
+                Command::Add("Front".to_string(), "Back".to_string()),
+            ),
+            ("add no separator", Command::Unknown),
+            ("ef", Command::Unknown),
+            ("delete", Command::Delete),
+            ("Suspend", Command::Suspend),
+            ("flag", Command::Flag),
             ("unknown", Command::Unknown),
         ];
 
@@ -188,21 +1060,121 @@ mod tests {
     }
 
     #[test]
-    fn test_load_from_csv() {
-        let data = "\
-front, back,
-front_1, back_1,
-front_2, back_2,
-front_3, back_3,
-";
+    fn test_card_text() {
+        let mut card = Card::new("front".to_string(), "back".to_string());
+        card.tags = vec!["a".to_string(), "b".to_string()];
+        card.notes = "some notes".to_string();
+        card.extra = vec![("source".to_string(), "book".to_string())];
+
+        let cases = vec![
+            ("front", false, "front\n\ntags: a b"),
+            (
+                "front",
+                true,
+                "front\n\nhint: this card has no hint\n\ntags: a b",
+            ),
+            (
+                "back",
+                true,
+                "back\n\nnotes: some notes\nsource: book\n\ntags: a b",
+            ),
+        ];
+
+        for (index, (state, show_hint, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                card_text(&card, state, show_hint),
+                expected,
+                "Case {index} failed"
+            );
+        }
+
+        card.hint = "a hint".to_string();
+        assert_eq!(
+            card_text(&card, "front", true),
+            "front\n\nhint: a hint\n\ntags: a b"
+        );
+    }
+
+    #[test]
+    fn test_card_label() {
+        let mut card = Card::new("front".to_string(), "back".to_string());
+        card.leitner.number = 2;
+        let mut turned = card.clone();
+        turned.reversed = true;
+        let mut flagged = card.clone();
+        flagged.flagged = true;
+
+        let cases = vec![
+            (&card, None, "rust"),
+            (&turned, None, "rust (reversed)"),
+            (&card, Some(5), "rust, box 2 of 5"),
+            (&turned, Some(3), "rust (reversed), box 2 of 3"),
+            (&flagged, None, "rust, flagged"),
+        ];
+
+        for (index, (card, boxes, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                card_label("rust", card, boxes),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_stats_report() {
+        let path =
+            std::env::temp_dir().join(format!("flashcards-{}-stats.csv", std::process::id()));
+        let mut deck = Deck::new(path).unwrap();
+        let cards = vec![
+            Card::new("one".to_string(), "1".to_string()),
+            Card::new("two".to_string(), "2".to_string()),
+        ];
+
+        // "one" was only ever studied back to front
+        let now = chrono::Utc::now();
+        let mut turned = direction::reversed(cards[0].clone());
+        turned.stats.record(Grade::Good, now, 2000);
+        deck.store.update(&turned).unwrap();
+        deck.store.log(Review {
+            card: turned.id(),
+            at: now,
+            grade: Grade::Good,
+            millis: 2000,
+        });
+
+        let report = stats_report(&[deck], cards, SystemClock.today());
+        assert_eq!(report.reviews, 1);
+        assert_eq!(report.millis, 2000);
+        // The two cards, and "one" turned around, but not "two" turned around
+        assert_eq!(report.decks[0].stats.cards, 3);
+    }
+
+    #[test]
+    fn test_suspended_cards() {
+        let path =
+            std::env::temp_dir().join(format!("flashcards-{}-suspended.csv", std::process::id()));
+        std::fs::write(&path, "front,back,suspended\na,1,true\nb,2,\n").unwrap();
+        let args = DeckArgs {
+            paths: vec![path.clone()],
+            format: None,
+            decks: Vec::new(),
+            tags: Vec::new(),
+            mappings: Vec::new(),
+        };
+
+        // Exports and stats still have the suspended card
+        let (decks, cards) = load_decks(&args).unwrap();
+        assert_eq!(export::rows(&decks, &cards).len(), 2);
+        let report = stats_report(&decks, cards, SystemClock.today());
+        assert_eq!(report.decks[0].stats.cards, 2);
 
-        let cards = load_from_csv(data.as_bytes()).unwrap();
+        // Sessions leave it out
+        let settings = session::tests::settings(Mode::Study, None);
+        let session = start_session(&args, &settings).unwrap().unwrap();
+        assert_eq!(session.view().total, 1);
+        assert_eq!(session.view().text, "b");
 
-        assert_eq!(cards[0].front(), "front_1");
-        assert_eq!(cards[0].back(), "back_1");
-        assert_eq!(cards[1].front(), "front_2");
-        assert_eq!(cards[1].back(), "back_2");
-        assert_eq!(cards[2].front(), "front_3");
-        assert_eq!(cards[2].back(), "back_3");
+        std::fs::remove_file(&path).unwrap();
     }
 }
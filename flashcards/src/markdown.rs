@@ -0,0 +1,449 @@
+use crossterm::style::{Attribute, Color, ContentStyle, Stylize};
+
+/// Words of the Rust language, highlighted in code blocks.
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+/// Text with a style for each character.
+type Styled = Vec<(char, ContentStyle)>;
+
+/// A line of rendered card text, with the terminal escapes for its styles.
+#[derive(Debug, PartialEq)]
+pub struct Line {
+    pub text: String,
+    /// Columns the line takes up on screen, leaving out the escapes.
+    pub width: usize,
+}
+
+/// Renders card text written in Markdown for the terminal, wrapped to
+/// `width` columns: headings, bold and italic text, lists, inline code and
+/// fenced code blocks. Code blocks are highlighted as Rust unless they name
+/// another language, the same as in rustdoc, and are broken rather than
+/// wrapped at the width.
+///
+/// Each line of the text is its own paragraph, as in the plain text of a
+/// card.
+pub fn render(text: &str, width: usize) -> Vec<Line> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    // Whether the code block the line is in is Rust
+    let mut fence = None;
+    let mut highlighter = Highlighter::default();
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+
+        if let Some(info) = trimmed.strip_prefix("```") {
+            fence = match fence {
+                Some(_) => None,
+                None => {
+                    highlighter = Highlighter::default();
+                    Some(is_rust(info.trim()))
+                }
+            };
+            continue;
+        }
+
+        if let Some(rust) = fence {
+            let chars = if rust {
+                highlighter.line(line)
+            } else {
+                line.chars().map(|c| (c, ContentStyle::new())).collect()
+            };
+            if chars.is_empty() {
+                lines.push(finish("", &[]));
+            }
+            for chunk in chars.chunks(width) {
+                lines.push(finish("", chunk));
+            }
+            continue;
+        }
+
+        if let Some(heading) = heading(trimmed) {
+            let bold = ContentStyle::new().bold();
+            lines.extend(wrap(&inline(heading, bold), width, "", ""));
+        } else if let Some((marker, item)) = list_item(trimmed) {
+            let indent = &line[..line.len() - trimmed.len()];
+            let first = format!("{indent}{marker}");
+            let rest = " ".repeat(first.chars().count());
+            lines.extend(wrap(
+                &inline(item, ContentStyle::new()),
+                width,
+                &first,
+                &rest,
+            ));
+        } else {
+            lines.extend(wrap(&inline(line, ContentStyle::new()), width, "", ""));
+        }
+    }
+
+    lines
+}
+
+fn is_rust(info: &str) -> bool {
+    let language = info.split([',', ' ']).next().unwrap_or("");
+
+    matches!(language, "" | "rust" | "rs")
+}
+
+/// The text of an ATX heading, such as `## Traits`.
+fn heading(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+
+    ((1..=6).contains(&level) && text.starts_with(' ')).then(|| text.trim())
+}
+
+/// The marker to show for a list item, and the item's text. Bullets are
+/// drawn as `•`; numbered items keep their number.
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(("• ".to_string(), item));
+        }
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        return Some((line[..digits + 2].to_string(), &rest[2..]));
+    }
+
+    None
+}
+
+/// Applies the inline styles: `**bold**`, `*italic*`, their underscore
+/// forms and `` `code` ``. Backslashes escape the markers.
+fn inline(text: &str, base: ContentStyle) -> Styled {
+    let chars: Vec<char> = text.chars().collect();
+    let mut styled = Vec::new();
+    let (mut bold, mut italic) = (false, false);
+    let code = ContentStyle::new().cyan();
+
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let mut style = base;
+        if bold {
+            style.attributes.set(Attribute::Bold);
+        }
+        if italic {
+            style.attributes.set(Attribute::Italic);
+        }
+
+        if c == '\\' && chars.get(index + 1).is_some_and(char::is_ascii_punctuation) {
+            styled.push((chars[index + 1], style));
+            index += 2;
+            continue;
+        }
+
+        if c == '`' {
+            if let Some(length) = chars[index + 1..].iter().position(|c| *c == '`') {
+                let span = &chars[index + 1..index + 1 + length];
+                styled.extend(span.iter().map(|c| (*c, code)));
+                index += length + 2;
+                continue;
+            }
+        }
+
+        if c == '*' || c == '_' {
+            let double = chars.get(index + 1) == Some(&c);
+            let count = if double { 2 } else { 1 };
+            let open = if double { &mut bold } else { &mut italic };
+
+            // Underscores inside words, as in snake_case, are left alone
+            let inside_word = c == '_'
+                && index > 0
+                && chars[index - 1].is_alphanumeric()
+                && chars
+                    .get(index + count)
+                    .is_some_and(|c| c.is_alphanumeric());
+            let rest = &chars[index + count..];
+            let closes_later = rest
+                .windows(count)
+                .any(|window| window.iter().all(|other| *other == c));
+            let opens = !rest.first().is_none_or(|next| next.is_whitespace()) && closes_later;
+
+            if !inside_word && (*open || opens) {
+                *open = !*open;
+                index += count;
+                continue;
+            }
+        }
+
+        styled.push((c, style));
+        index += 1;
+    }
+
+    styled
+}
+
+/// Wraps styled text at whitespace, starting the first line with `first`
+/// and the others with `rest`. Words longer than a line are broken.
+fn wrap(text: &[(char, ContentStyle)], width: usize, first: &str, rest: &str) -> Vec<Line> {
+    let available = width.saturating_sub(first.chars().count()).max(1);
+    let mut lines = Vec::new();
+    let mut line: Styled = Vec::new();
+    let prefix = |lines: &Vec<Line>| if lines.is_empty() { first } else { rest };
+
+    for word in text.split(|(c, _)| c.is_whitespace()) {
+        if word.is_empty() {
+            continue;
+        }
+        let mut word = word.to_vec();
+
+        while word.len() > available {
+            if !line.is_empty() {
+                lines.push(finish(prefix(&lines), &std::mem::take(&mut line)));
+            }
+            let head: Styled = word.drain(..available).collect();
+            lines.push(finish(prefix(&lines), &head));
+        }
+
+        if !line.is_empty() && line.len() + 1 + word.len() > available {
+            lines.push(finish(prefix(&lines), &std::mem::take(&mut line)));
+        }
+        if !line.is_empty() {
+            line.push((' ', ContentStyle::new()));
+        }
+        line.extend(word);
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(finish(prefix(&lines), &line));
+    }
+
+    lines
+}
+
+/// Turns styled text into a line, with an escape for each run of a style.
+fn finish(prefix: &str, text: &[(char, ContentStyle)]) -> Line {
+    let mut line = prefix.to_string();
+
+    for run in text.chunk_by(|(_, a), (_, b)| a == b) {
+        let run_text: String = run.iter().map(|(c, _)| c).collect();
+        let style = run[0].1;
+        if style == ContentStyle::new() {
+            line.push_str(&run_text);
+        } else {
+            line.push_str(&style.apply(run_text).to_string());
+        }
+    }
+
+    Line {
+        text: line,
+        width: prefix.chars().count() + text.len(),
+    }
+}
+
+/// Highlights Rust a line at a time, keeping track of block comments that
+/// run over several lines.
+#[derive(Default)]
+struct Highlighter {
+    in_comment: bool,
+}
+
+impl Highlighter {
+    fn line(&mut self, line: &str) -> Styled {
+        let chars: Vec<char> = line.chars().collect();
+        let mut styled = Vec::new();
+        let mut push = |chars: &[char], color: Option<Color>| {
+            let style = color.map_or(ContentStyle::new(), |color| ContentStyle::new().with(color));
+            styled.extend(chars.iter().map(|c| (*c, style)));
+        };
+
+        let mut index = 0;
+        while index < chars.len() {
+            let rest = &chars[index..];
+
+            let (length, token) = if self.in_comment || rest.starts_with(&['/', '*']) {
+                let start = if self.in_comment { 0 } else { 2 };
+                let end = rest[start..].windows(2).position(|pair| pair == ['*', '/']);
+                self.in_comment = end.is_none();
+                (
+                    end.map_or(rest.len(), |end| start + end + 2),
+                    Some(Color::DarkGrey),
+                )
+            } else if rest.starts_with(&['/', '/']) {
+                (rest.len(), Some(Color::DarkGrey))
+            } else if rest[0] == '"' {
+                let mut end = 1;
+                while end < rest.len() && rest[end] != '"' {
+                    end += if rest[end] == '\\' { 2 } else { 1 };
+                }
+                ((end + 1).min(rest.len()), Some(Color::Green))
+            } else if rest[0] == '\'' {
+                // 'a' and '\n' are characters, 'a on its own a lifetime
+                let escaped = rest.get(1) == Some(&'\\');
+                let close = if escaped { 3 } else { 2 };
+                if rest.get(close) == Some(&'\'') {
+                    (close + 1, Some(Color::Green))
+                } else {
+                    (1 + identifier(&rest[1..]), Some(Color::Cyan))
+                }
+            } else if rest[0].is_ascii_digit() {
+                let length = rest
+                    .iter()
+                    .position(|c| !(c.is_alphanumeric() || *c == '_' || *c == '.'))
+                    .unwrap_or(rest.len());
+                (length, Some(Color::Cyan))
+            } else if rest[0].is_alphabetic() || rest[0] == '_' {
+                let length = identifier(rest);
+                let word: String = rest[..length].iter().collect();
+                if rest.get(length) == Some(&'!') {
+                    (length + 1, Some(Color::Blue))
+                } else if KEYWORDS.contains(&word.as_str()) {
+                    (length, Some(Color::Magenta))
+                } else if rest[0].is_uppercase() {
+                    (length, Some(Color::Yellow))
+                } else {
+                    (length, None)
+                }
+            } else {
+                (1, None)
+            };
+
+            push(&rest[..length], token);
+            index += length;
+        }
+
+        styled
+    }
+}
+
+/// Length of the identifier at the start of `chars`.
+fn identifier(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .position(|c| !(c.is_alphanumeric() || *c == '_'))
+        .unwrap_or(chars.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of rendered lines without their escapes.
+    fn plain(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| {
+                let mut text = String::new();
+                let mut chars = line.text.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\x1b' {
+                        chars.by_ref().find(|c| *c == 'm');
+                    } else {
+                        text.push(c);
+                    }
+                }
+                assert_eq!(text.chars().count(), line.width);
+                text
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let cases = vec![
+            ("plain text", vec!["plain text"]),
+            ("wrap these words", vec!["wrap these", "words"]),
+            (
+                "a **bold** and *italic* `x`",
+                vec!["a bold and", "italic x"],
+            ),
+            ("# Traits", vec!["Traits"]),
+            (
+                "- first item\n  2. nested one",
+                vec!["• first", "  item", "  2. nested", "     one"],
+            ),
+            (
+                "```\nlet x = 1;\n\n```\nafter",
+                vec!["let x = 1;", "", "after"],
+            ),
+            (
+                "```\nfn a_long_name() {}\n```",
+                vec!["fn a_long_n", "ame() {}"],
+            ),
+            ("snake_case and 2 * 3", vec!["snake_case", "and 2 * 3"]),
+            ("", vec![]),
+            ("line\n\nline", vec!["line", "", "line"]),
+        ];
+
+        for (index, (text, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(plain(&render(text, 11)), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_inline() {
+        let bold = ContentStyle::new().bold();
+        let italic = ContentStyle::new().italic();
+        let code = ContentStyle::new().cyan();
+        let plain = ContentStyle::new();
+
+        let cases = vec![
+            ("**a**", vec![('a', bold)]),
+            ("_a_", vec![('a', italic)]),
+            ("`*`", vec![('*', code)]),
+            ("\\*a*", vec![('*', plain), ('a', plain), ('*', plain)]),
+            ("a_b", vec![('a', plain), ('_', plain), ('b', plain)]),
+        ];
+
+        for (index, (text, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(inline(text, plain), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_highlight() {
+        let colors = |line: &str| -> Vec<(String, Option<Color>)> {
+            Highlighter::default()
+                .line(line)
+                .chunk_by(|(_, a), (_, b)| a == b)
+                .map(|run| {
+                    let text = run.iter().map(|(c, _)| c).collect();
+                    (text, run[0].1.foreground_color)
+                })
+                .collect()
+        };
+        let token = |text: &str, color| (text.to_string(), color);
+
+        assert_eq!(
+            colors("let s: &'a str = \"hi\"; // ok"),
+            vec![
+                token("let", Some(Color::Magenta)),
+                token(" s: &", None),
+                token("'a", Some(Color::Cyan)),
+                token(" str = ", None),
+                token("\"hi\"", Some(Color::Green)),
+                token("; ", None),
+                token("// ok", Some(Color::DarkGrey)),
+            ]
+        );
+        assert_eq!(
+            colors("println!('x', Vec::new(), 42)"),
+            vec![
+                token("println!", Some(Color::Blue)),
+                token("(", None),
+                token("'x'", Some(Color::Green)),
+                token(", ", None),
+                token("Vec", Some(Color::Yellow)),
+                token("::new(), ", None),
+                token("42", Some(Color::Cyan)),
+                token(")", None),
+            ]
+        );
+
+        let mut highlighter = Highlighter::default();
+        highlighter.line("/* a");
+        assert!(highlighter.in_comment);
+        let line = highlighter.line("b */ x");
+        assert_eq!(line[0].1.foreground_color, Some(Color::DarkGrey));
+        assert_eq!(line[5].1.foreground_color, None);
+    }
+}
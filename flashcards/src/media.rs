@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+
+use serde::Serialize;
+
+use crate::Card;
+
+/// Rows of the terminal an image takes up.
+pub const IMAGE_ROWS: usize = 10;
+
+/// Pixels per row assumed when scaling images for sixel, which is drawn in
+/// pixels rather than cells.
+const PIXELS_PER_ROW: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Image,
+    Sound,
+}
+
+/// A media file a card refers to, with its path resolved against the
+/// card's deck.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Attachment {
+    pub kind: Kind,
+    pub path: PathBuf,
+}
+
+/// The media files a card's text refers to, written as `[img:path]` or
+/// `[sound:path]`, with their kind.
+pub fn attachments(text: &str) -> Vec<(Kind, &str)> {
+    let mut found = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+
+        let Some((kind, reference)) = [("img:", Kind::Image), ("sound:", Kind::Sound)]
+            .iter()
+            .find_map(|(prefix, kind)| Some((*kind, rest.strip_prefix(prefix)?)))
+        else {
+            continue;
+        };
+        if let Some(end) = reference.find(']') {
+            let path = reference[..end].trim();
+            if !path.is_empty() {
+                found.push((kind, path));
+            }
+            rest = &reference[end + 1..];
+        }
+    }
+
+    found
+}
+
+/// Paths of the media files a card's text refers to. Paths are relative to
+/// the deck's directory.
+pub fn references(text: &str) -> Vec<&str> {
+    attachments(text)
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// The attachments of `text`, for a deck in `dir`. Files that do not exist
+/// are left out, so that only their reference in the text is shown.
+pub fn resolve(text: &str, dir: &Path) -> Vec<Attachment> {
+    attachments(text)
+        .into_iter()
+        .map(|(kind, path)| Attachment {
+            kind,
+            path: dir.join(path),
+        })
+        .filter(|attachment| attachment.path.is_file())
+        .collect()
+}
+
+/// Whether any media file the card refers to is missing from `dir`.
+pub fn is_missing_media(card: &Card, dir: &Path) -> bool {
+    [card.front(), card.back(), &card.hint, &card.notes]
+        .into_iter()
+        .flat_map(references)
+        .any(|file| !dir.join(file).is_file())
+}
+
+/// How images are drawn in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Graphics {
+    /// The kitty graphics protocol, also spoken by WezTerm and Ghostty.
+    Kitty,
+    Sixel,
+    /// Images are left as their `[img:path]` reference.
+    Text,
+}
+
+pub fn parse_graphics(input: &str) -> Option<Graphics> {
+    match input {
+        "kitty" => Some(Graphics::Kitty),
+        "sixel" => Some(Graphics::Sixel),
+        "text" => Some(Graphics::Text),
+        _ => None,
+    }
+}
+
+/// Guesses the graphics the terminal supports from its environment.
+pub fn detect() -> Graphics {
+    detect_from(|name| std::env::var(name).ok())
+}
+
+fn detect_from(var: impl Fn(&str) -> Option<String>) -> Graphics {
+    let term = var("TERM").unwrap_or_default();
+    let program = var("TERM_PROGRAM").unwrap_or_default();
+
+    if var("KITTY_WINDOW_ID").is_some()
+        || matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+        || matches!(program.as_str(), "WezTerm" | "ghostty")
+    {
+        Graphics::Kitty
+    } else if ["foot", "mlterm", "contour"]
+        .iter()
+        .any(|name| term.starts_with(name))
+        || term.contains("sixel")
+        || program == "iTerm.app"
+    {
+        Graphics::Sixel
+    } else {
+        Graphics::Text
+    }
+}
+
+/// Draws the images of cards, keeping the escapes of each so redrawing a
+/// card does not read its image again.
+pub struct Images {
+    graphics: Graphics,
+    drawn: HashMap<PathBuf, Option<String>>,
+}
+
+impl Images {
+    pub fn new(graphics: Graphics) -> Self {
+        Self {
+            graphics,
+            drawn: HashMap::new(),
+        }
+    }
+
+    /// The escapes that draw the image at `path` at the cursor, or `None`
+    /// when it cannot be drawn and its reference has to do. Only PNG
+    /// images are drawn.
+    pub fn draw(&mut self, path: &Path) -> Option<&str> {
+        let graphics = self.graphics;
+        self.drawn
+            .entry(path.to_path_buf())
+            .or_insert_with(|| match graphics {
+                Graphics::Kitty => kitty(path).ok(),
+                Graphics::Sixel => sixel(path).ok(),
+                Graphics::Text => None,
+            })
+            .as_deref()
+    }
+
+    /// The escape that takes down images drawn before. Clearing the screen
+    /// does this for sixel, but kitty keeps its images until told.
+    pub fn clear(&self) -> &'static str {
+        match self.graphics {
+            Graphics::Kitty => "\x1b_Ga=d,q=2\x1b\\",
+            _ => "",
+        }
+    }
+}
+
+/// Has kitty read the PNG file itself, scaled to the image rows.
+fn kitty(path: &Path) -> std::io::Result<String> {
+    let path = path.canonicalize()?;
+    let mut signature = [0; 8];
+    std::io::Read::read_exact(&mut std::fs::File::open(&path)?, &mut signature)?;
+    if signature != *b"\x89PNG\r\n\x1a\n" {
+        return Err(std::io::Error::other("not a PNG image"));
+    }
+
+    // q=2 keeps kitty from answering, which would show up as typed keys
+    Ok(format!(
+        "\x1b_Ga=T,f=100,t=f,q=2,r={IMAGE_ROWS};{}\x1b\\",
+        base64(path.to_string_lossy().as_bytes())
+    ))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (index, byte)| {
+            n | u32::from(*byte) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(n >> (18 - 6 * index) & 63) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes a PNG image and encodes it as sixel.
+fn sixel(path: &Path) -> std::io::Result<String> {
+    let mut decoder = png::Decoder::new(std::fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(std::io::Error::other)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(std::io::Error::other)?;
+
+    let channels = info.color_type.samples();
+    let pixels: Vec<Option<[u8; 3]>> = buffer[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| {
+            let (color, alpha) = match pixel {
+                [gray] => ([*gray; 3], 255),
+                [gray, alpha] => ([*gray; 3], *alpha),
+                [r, g, b] => ([*r, *g, *b], 255),
+                [r, g, b, alpha, ..] => ([*r, *g, *b], *alpha),
+                [] => ([0; 3], 0),
+            };
+            (alpha >= 128).then_some(color)
+        })
+        .collect();
+
+    Ok(encode_sixel(
+        &pixels,
+        info.width as usize,
+        info.height as usize,
+        IMAGE_ROWS * PIXELS_PER_ROW,
+    ))
+}
+
+/// Encodes pixels, `None` where transparent, as sixel, scaled down to at
+/// most `max_height` pixels. Colors are rounded to a 6x6x6 color cube.
+fn encode_sixel(
+    pixels: &[Option<[u8; 3]>],
+    width: usize,
+    height: usize,
+    max_height: usize,
+) -> String {
+    let scale = height.div_ceil(max_height).max(1);
+    let (width, height, source_width) = (width / scale, height / scale, width);
+    let index = |[r, g, b]: [u8; 3]| {
+        let level = |value: u8| (usize::from(value) * 5 + 127) / 255;
+        level(r) * 36 + level(g) * 6 + level(b)
+    };
+    let pixel = |x: usize, y: usize| pixels[y * scale * source_width + x * scale].map(index);
+
+    // P2=1 leaves transparent pixels as they are
+    let mut sixel = format!("\x1bP0;1q\"1;1;{width};{height}");
+    let mut used = [false; 216];
+    for y in 0..height {
+        for x in 0..width {
+            if let Some(color) = pixel(x, y) {
+                used[color] = true;
+            }
+        }
+    }
+    for color in (0..216).filter(|color| used[*color]) {
+        let percent = |level: usize| level * 100 / 5;
+        sixel.push_str(&format!(
+            "#{color};2;{};{};{}",
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        ));
+    }
+
+    for band in (0..height).step_by(6) {
+        for color in (0..216).filter(|color| used[*color]) {
+            let bits: Vec<u8> = (0..width)
+                .map(|x| {
+                    (0..6)
+                        .filter(|row| band + row < height && pixel(x, band + row) == Some(color))
+                        .fold(0, |bits, row| bits | 1 << row)
+                })
+                .collect();
+            if bits.iter().all(|bits| *bits == 0) {
+                continue;
+            }
+
+            sixel.push_str(&format!("#{color}"));
+            for run in bits.chunk_by(|a, b| a == b) {
+                let c = char::from(63 + run[0]);
+                if run.len() > 3 {
+                    sixel.push_str(&format!("!{}{c}", run.len()));
+                } else {
+                    sixel.extend(std::iter::repeat_n(c, run.len()));
+                }
+            }
+            sixel.push('$');
+        }
+        sixel.push('-');
+    }
+
+    sixel.push_str("\x1b\\");
+    sixel
+}
+
+/// Plays sounds with an external command, such as `mpv --no-video`. The
+/// sound files are put in place of a `{}` in the command, or after it.
+pub struct Player {
+    command: Vec<String>,
+    playing: Option<Child>,
+}
+
+impl Player {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.split_whitespace().map(str::to_string).collect(),
+            playing: None,
+        }
+    }
+
+    /// Starts playing `paths`, stopping whatever was playing before.
+    pub fn play(&mut self, paths: &[&Path]) -> std::io::Result<()> {
+        self.stop();
+        let Some((program, args)) = self.command.split_first() else {
+            return Ok(());
+        };
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut command = std::process::Command::new(program);
+        if args.iter().any(|arg| arg == "{}") {
+            for arg in args {
+                if arg == "{}" {
+                    command.args(paths);
+                } else {
+                    command.arg(arg);
+                }
+            }
+        } else {
+            command.args(args).args(paths);
+        }
+
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        self.playing = Some(child);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut child) = self.playing.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        let cases = vec![
+            ("no media", vec![]),
+            ("[img:ferris.png]", vec!["ferris.png"]),
+            (
+                "listen [sound: audio/hello.ogg ] and look [img:a.png]",
+                vec!["audio/hello.ogg", "a.png"],
+            ),
+            ("[img:] [video:a.mp4] [img:open", vec![]),
+            ("[[img:nested.png]]", vec!["nested.png"]),
+        ];
+
+        for (index, (text, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(references(text), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_attachments() {
+        let dir = std::env::temp_dir();
+        let file = format!("flashcards-{}-media.png", std::process::id());
+        std::fs::write(dir.join(&file), b"").unwrap();
+        let text = format!("[sound:missing.ogg] [img:{file}]");
+
+        assert_eq!(
+            attachments(&text),
+            vec![(Kind::Sound, "missing.ogg"), (Kind::Image, file.as_str())]
+        );
+        assert_eq!(
+            resolve(&text, &dir),
+            vec![Attachment {
+                kind: Kind::Image,
+                path: dir.join(&file),
+            }]
+        );
+
+        let cases = vec![
+            (format!("[img:{file}]"), false),
+            ("[img:missing.png]".to_string(), true),
+            ("no media".to_string(), false),
+        ];
+        for (index, (back, expected)) in cases.into_iter().enumerate() {
+            let card = Card::new("front".to_string(), back);
+            assert_eq!(
+                is_missing_media(&card, &dir),
+                expected,
+                "Case {index} failed"
+            );
+        }
+        std::fs::remove_file(dir.join(file)).unwrap();
+    }
+
+    #[test]
+    fn test_detect_from() {
+        let cases = vec![
+            (vec![("KITTY_WINDOW_ID", "1")], Graphics::Kitty),
+            (vec![("TERM_PROGRAM", "WezTerm")], Graphics::Kitty),
+            (vec![("TERM", "foot-extra")], Graphics::Sixel),
+            (vec![("TERM", "xterm-256color")], Graphics::Text),
+            (vec![], Graphics::Text),
+        ];
+
+        for (index, (vars, expected)) in cases.into_iter().enumerate() {
+            let var = |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            };
+            assert_eq!(detect_from(var), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let cases = vec![("", ""), ("a", "YQ=="), ("ab", "YWI="), ("abc", "YWJj")];
+        for (index, (bytes, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(base64(bytes.as_bytes()), expected, "Case {index} failed");
+        }
+
+        let red = Some([255, 0, 0]);
+        assert_eq!(
+            encode_sixel(&[red, red, red, red, None, red], 6, 1, 10),
+            "\x1bP0;1q\"1;1;6;1#180;2;100;0;0#180!4@?@$-\x1b\\"
+        );
+    }
+}
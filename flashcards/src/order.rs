@@ -0,0 +1,222 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::Card;
+
+/// The order a session goes through its cards in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    /// As the cards appear in their deck files.
+    File,
+    Shuffle,
+    /// By front, ignoring case.
+    Alphabetical,
+    /// Highest share of "again" grades first.
+    Weakest,
+    /// Least recently reviewed first, never reviewed before all others.
+    Oldest,
+    /// Longest average response time first, cards never timed last.
+    Slowest,
+    /// Cycles through the tags, so no single topic comes in a long run.
+    Interleaved,
+}
+
+/// Every order, in the sequence the in-session order command steps through.
+const ORDERS: [Order; 7] = [
+    Order::File,
+    Order::Shuffle,
+    Order::Alphabetical,
+    Order::Weakest,
+    Order::Oldest,
+    Order::Slowest,
+    Order::Interleaved,
+];
+
+pub fn parse_order(input: &str) -> Option<Order> {
+    ORDERS.into_iter().find(|order| order.name() == input)
+}
+
+impl Order {
+    pub fn name(self) -> &'static str {
+        match self {
+            Order::File => "file",
+            Order::Shuffle => "shuffle",
+            Order::Alphabetical => "alphabetical",
+            Order::Weakest => "weakest",
+            Order::Oldest => "oldest",
+            Order::Slowest => "slowest",
+            Order::Interleaved => "interleaved",
+        }
+    }
+
+    /// The order after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let index = ORDERS.iter().position(|order| *order == self).unwrap_or(0);
+        ORDERS[(index + 1) % ORDERS.len()]
+    }
+
+    /// Reorders `queue`, a list of indexes into `cards`. All the orders but
+    /// the shuffle are stable, keeping file order between equal cards.
+    pub fn apply(self, cards: &[Card], queue: &mut Vec<usize>, rng: &mut impl Rng) {
+        match self {
+            Order::File => queue.sort(),
+            Order::Shuffle => queue.shuffle(rng),
+            Order::Alphabetical => {
+                queue.sort();
+                queue.sort_by_cached_key(|index| cards[*index].front().to_lowercase());
+            }
+            Order::Weakest => {
+                queue.sort();
+                queue.sort_by(|a, b| error_rate(&cards[*b]).total_cmp(&error_rate(&cards[*a])));
+            }
+            Order::Oldest => {
+                queue.sort();
+                queue.sort_by_key(|index| cards[*index].stats.last_seen);
+            }
+            Order::Slowest => {
+                queue.sort();
+                queue.sort_by_key(|index| Reverse(cards[*index].stats.average_millis()));
+            }
+            Order::Interleaved => {
+                queue.sort();
+                *queue = interleave(cards, queue);
+            }
+        }
+    }
+}
+
+/// Share of reviews graded "again"; zero for cards never reviewed.
+fn error_rate(card: &Card) -> f64 {
+    let reviews = card.stats.correct + card.stats.incorrect;
+
+    if reviews == 0 {
+        0.0
+    } else {
+        f64::from(card.stats.incorrect) / f64::from(reviews)
+    }
+}
+
+/// Groups the cards by their first tag and takes one from each group in
+/// turn. Groups go in the order their first card appears.
+fn interleave(cards: &[Card], queue: &[usize]) -> Vec<usize> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of: HashMap<&str, usize> = HashMap::new();
+
+    for &index in queue {
+        let tag = cards[index].tags.first().map_or("", String::as_str);
+        let group = *group_of.entry(tag).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(index);
+    }
+
+    let longest = groups.iter().map(Vec::len).max().unwrap_or(0);
+    (0..longest)
+        .flat_map(|round| groups.iter().filter_map(move |group| group.get(round)))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn deck() -> Vec<Card> {
+        let mut cards: Vec<Card> = ["delta", "Alpha", "charlie", "bravo", "echo"]
+            .iter()
+            .map(|front| Card::new(front.to_string(), "back".to_string()))
+            .collect();
+
+        // Error rates of 50%, 0%, 100%, unseen and 25%
+        for (card, (correct, incorrect)) in
+            cards
+                .iter_mut()
+                .zip([(1, 1), (2, 0), (0, 1), (0, 0), (3, 1)])
+        {
+            card.stats.correct = correct;
+            card.stats.incorrect = incorrect;
+        }
+        for (card, day) in cards
+            .iter_mut()
+            .zip([Some(3), Some(1), Some(2), None, Some(5)])
+        {
+            card.stats.last_seen =
+                day.map(|day| Utc.with_ymd_and_hms(2024, 3, day, 0, 0, 0).unwrap());
+        }
+        for (card, millis) in cards.iter_mut().zip([2000, 0, 9000, 0, 4000]) {
+            if millis > 0 {
+                card.stats.timed = 2;
+                card.stats.millis = millis;
+            }
+        }
+        for (card, tag) in cards
+            .iter_mut()
+            .zip(["verbs", "nouns", "verbs", "", "verbs"])
+        {
+            if !tag.is_empty() {
+                card.tags = vec![tag.to_string()];
+            }
+        }
+
+        cards
+    }
+
+    #[test]
+    fn test_parse_order() {
+        for order in ORDERS {
+            assert_eq!(parse_order(order.name()), Some(order));
+        }
+        assert_eq!(parse_order("random"), None);
+    }
+
+    #[test]
+    fn test_next() {
+        assert_eq!(Order::File.next(), Order::Shuffle);
+        assert_eq!(Order::Interleaved.next(), Order::File);
+    }
+
+    #[test]
+    fn test_apply() {
+        let cards = deck();
+        let mut rng = StdRng::seed_from_u64(7);
+        let cases = vec![
+            (Order::File, vec![0, 1, 2, 3, 4]),
+            (Order::Alphabetical, vec![1, 3, 2, 0, 4]),
+            (Order::Weakest, vec![2, 0, 4, 1, 3]),
+            (Order::Oldest, vec![3, 1, 2, 0, 4]),
+            (Order::Slowest, vec![2, 4, 0, 1, 3]),
+            (Order::Interleaved, vec![0, 1, 3, 2, 4]),
+        ];
+
+        for (index, (order, expected)) in cases.into_iter().enumerate() {
+            let mut queue = vec![4, 3, 2, 1, 0];
+            order.apply(&cards, &mut queue, &mut rng);
+            assert_eq!(queue, expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_shuffle_is_seeded() {
+        let cards = deck();
+        let shuffled = |seed| {
+            let mut queue = vec![0, 1, 2, 3, 4];
+            Order::Shuffle.apply(&cards, &mut queue, &mut StdRng::seed_from_u64(seed));
+            queue
+        };
+
+        assert_eq!(shuffled(7), shuffled(7));
+        let mut sorted = shuffled(7);
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2, 3, 4]);
+    }
+}
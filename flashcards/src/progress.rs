@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::leitner::Placement;
+use crate::scheduler::{Grade, Schedule};
+use crate::Card;
+
+/// Number of reviews after which progress is written to disk, so a crash
+/// never loses more than a handful of answers.
+const AUTOSAVE_EVERY: usize = 5;
+
+/// Stable identity of a card, derived from its front and back.
+///
+/// This is a 64-bit FNV-1a hash rendered as hex. Unlike `DefaultHasher` its
+/// output never changes between Rust releases, so it is safe to persist.
+pub fn card_id(front: &str, back: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    // The unit separator keeps ("ab", "c") and ("a", "bc") apart
+    for byte in front.bytes().chain([0x1f]).chain(back.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+
+    format!("{hash:016x}")
+}
+
+/// Review counters kept for every card.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub last_seen: Option<DateTime<Utc>>,
+    pub correct: u32,
+    pub incorrect: u32,
+    /// Reviews whose response time was measured, and the total of those
+    /// times. Progress from before times were kept has neither.
+    #[serde(default)]
+    pub timed: u32,
+    #[serde(default)]
+    pub millis: u64,
+}
+
+impl Stats {
+    pub fn record(&mut self, grade: Grade, now: DateTime<Utc>, millis: u64) {
+        self.last_seen = Some(now);
+        self.timed += 1;
+        self.millis += millis;
+
+        if grade == Grade::Again {
+            self.incorrect += 1;
+        } else {
+            self.correct += 1;
+        }
+    }
+
+    /// Mean response time in milliseconds, if any review was timed.
+    pub fn average_millis(&self) -> Option<u64> {
+        self.millis.checked_div(u64::from(self.timed))
+    }
+}
+
+/// Everything remembered about a single card between sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    #[serde(flatten)]
+    pub stats: Stats,
+    pub schedule: Schedule,
+    /// Only changes in Leitner sessions; files from before have none.
+    #[serde(default)]
+    pub leitner: Placement,
+}
+
+/// One answer given in a session, kept so that history can be reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Review {
+    /// The [`card_id`] of the card that was answered.
+    pub card: String,
+    pub at: DateTime<Utc>,
+    pub grade: Grade,
+    /// Time from showing the card to grading it.
+    pub millis: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProgressFile {
+    cards: BTreeMap<String, Progress>,
+    /// Oldest first. Files written before the log existed have none.
+    #[serde(default)]
+    reviews: Vec<Review>,
+}
+
+/// Review progress for one deck, stored as JSON next to the deck file.
+///
+/// Entries are keyed by [`card_id`], so editing a card in the deck only
+/// resets that card. Entries for cards that are no longer in the deck are
+/// kept, which means undoing an edit brings the old progress back.
+pub struct ProgressStore {
+    path: PathBuf,
+    file: ProgressFile,
+    unsaved: usize,
+}
+
+impl ProgressStore {
+    /// The sidecar path used for a deck, e.g. `deck.csv.progress.json`.
+    pub fn sidecar_path(deck_path: &Path) -> PathBuf {
+        let mut path = deck_path.as_os_str().to_owned();
+        path.push(".progress.json");
+        PathBuf::from(path)
+    }
+
+    /// Loads the store at `path`, starting empty if it does not exist yet.
+    pub fn load(path: PathBuf) -> std::io::Result<Self> {
+        let file = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => ProgressFile::default(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            path,
+            file,
+            unsaved: 0,
+        })
+    }
+
+    /// Copies the stored progress onto the matching cards.
+    pub fn apply(&self, cards: &mut [Card]) {
+        for card in cards {
+            if let Some(progress) = self.file.cards.get(&card.id()) {
+                card.stats = progress.stats.clone();
+                card.schedule = progress.schedule.clone();
+                card.leitner = progress.leitner.clone();
+            }
+        }
+    }
+
+    /// Every review logged so far, oldest first.
+    pub fn reviews(&self) -> &[Review] {
+        &self.file.reviews
+    }
+
+    /// Adds a review to the log. It is written out with the next save.
+    pub fn log(&mut self, review: Review) {
+        self.file.reviews.push(review);
+    }
+
+    /// Records the card's current progress, saving every few reviews.
+    pub fn update(&mut self, card: &Card) -> std::io::Result<()> {
+        self.file.cards.insert(
+            card.id(),
+            Progress {
+                stats: card.stats.clone(),
+                schedule: card.schedule.clone(),
+                leitner: card.leitner.clone(),
+            },
+        );
+
+        self.unsaved += 1;
+        if self.unsaved >= AUTOSAVE_EVERY {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the store to disk atomically (temp file + rename).
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let mut tmp_path = self.path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.file)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        std::fs::rename(&tmp_path, &self.path)?;
+        self.unsaved = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("flashcards-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_card_id() {
+        assert_eq!(card_id("front", "back"), card_id("front", "back"));
+        assert_ne!(card_id("front", "back"), card_id("back", "front"));
+        assert_ne!(card_id("ab", "c"), card_id("a", "bc"));
+        assert_eq!(card_id("", ""), "af63d24c8601db8e");
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        let path = ProgressStore::sidecar_path(Path::new("decks/rust.csv"));
+
+        assert_eq!(path, PathBuf::from("decks/rust.csv.progress.json"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("save_and_load.json");
+        let now = Utc::now();
+
+        let mut card = Card::new("front".to_string(), "back".to_string());
+        card.stats.record(Grade::Good, now, 1500);
+        card.stats.record(Grade::Again, now, 4500);
+        card.schedule.interval = 6;
+        card.leitner.number = 3;
+
+        let review = Review {
+            card: card.id(),
+            at: now,
+            grade: Grade::Again,
+            millis: 1500,
+        };
+
+        let mut store = ProgressStore::load(path.clone()).unwrap();
+        store.log(review.clone());
+        store.update(&card).unwrap();
+        store.save().unwrap();
+
+        let mut cards = vec![
+            Card::new("front".to_string(), "back".to_string()),
+            Card::new("front".to_string(), "edited back".to_string()),
+        ];
+        let store = ProgressStore::load(path.clone()).unwrap();
+        store.apply(&mut cards);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(store.reviews(), [review]);
+
+        assert_eq!(cards[0].stats, card.stats);
+        assert_eq!(cards[0].stats.correct, 1);
+        assert_eq!(cards[0].stats.incorrect, 1);
+        assert_eq!(cards[0].stats.average_millis(), Some(3000));
+        assert_eq!(cards[0].schedule.interval, 6);
+        assert_eq!(cards[0].leitner.number, 3);
+        assert_eq!(cards[1].stats, Stats::default());
+        assert_eq!(cards[1].stats.average_millis(), None);
+        assert_eq!(cards[1].schedule, Schedule::default());
+    }
+
+    #[test]
+    fn test_autosave() {
+        let path = temp_path("autosave.json");
+        let card = Card::new("front".to_string(), "back".to_string());
+
+        let mut store = ProgressStore::load(path.clone()).unwrap();
+        for _ in 0..AUTOSAVE_EVERY - 1 {
+            store.update(&card).unwrap();
+        }
+        assert!(!path.exists());
+
+        store.update(&card).unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use serde::Serialize;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Default number of character edits a typed answer may be off by.
+pub const DEFAULT_THRESHOLD: usize = 1;
+
+/// Characters of the expected answer per edit forgiven, so that a short
+/// answer such as `4` has to be typed exactly.
+const CHARS_PER_EDIT: usize = 4;
+
+/// Normalizes an answer so that case, whitespace, punctuation and
+/// diacritics do not count against the user.
+pub fn normalize(text: &str) -> String {
+    let stripped: String = text
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Levenshtein distance between two strings, counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Outcome of checking one typed answer.
+#[derive(Debug, PartialEq)]
+pub struct Answer {
+    pub correct: bool,
+    pub distance: usize,
+}
+
+/// Checks a typed answer, forgiving up to `threshold` edits, but no more
+/// than one for every [`CHARS_PER_EDIT`] characters of the expected answer.
+pub fn check_answer(typed: &str, expected: &str, threshold: usize) -> Answer {
+    let expected = normalize(expected);
+    let distance = edit_distance(&normalize(typed), &expected);
+    let allowed = threshold.min(expected.chars().count() / CHARS_PER_EDIT);
+
+    Answer {
+        correct: distance <= allowed,
+        distance,
+    }
+}
+
+/// Character diff between the typed and expected answers.
+///
+/// Characters only in the typed answer are shown as `[-x-]` and characters
+/// only in the expected answer as `{+x+}`, like `git diff --word-diff`.
+pub fn diff(typed: &str, expected: &str) -> String {
+    let typed: Vec<char> = typed.chars().collect();
+    let expected: Vec<char> = expected.chars().collect();
+
+    // lcs[i][j] is the longest common subsequence of typed[i..] and expected[j..]
+    let mut lcs = vec![vec![0; expected.len() + 1]; typed.len() + 1];
+    for i in (0..typed.len()).rev() {
+        for j in (0..expected.len()).rev() {
+            lcs[i][j] = if typed[i] == expected[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let mut removed = String::new();
+    let mut added = String::new();
+    let flush = |output: &mut String, removed: &mut String, added: &mut String| {
+        if !removed.is_empty() {
+            output.push_str(&format!("[-{removed}-]"));
+            removed.clear();
+        }
+        if !added.is_empty() {
+            output.push_str(&format!("{{+{added}+}}"));
+            added.clear();
+        }
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < typed.len() || j < expected.len() {
+        if i < typed.len() && j < expected.len() && typed[i] == expected[j] {
+            flush(&mut output, &mut removed, &mut added);
+            output.push(typed[i]);
+            i += 1;
+            j += 1;
+        } else if j == expected.len() || (i < typed.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            removed.push(typed[i]);
+            i += 1;
+        } else {
+            added.push(expected[j]);
+            j += 1;
+        }
+    }
+    flush(&mut output, &mut removed, &mut added);
+
+    output
+}
+
+/// Feedback shown after an answer has been checked.
+pub fn feedback(answer: &Answer, typed: &str, expected: &str) -> String {
+    let verdict = if answer.correct {
+        "Correct!".to_string()
+    } else {
+        format!("Incorrect ({} edits away).", answer.distance)
+    };
+
+    format!(
+        "{verdict}
+expected: {expected}
+    diff: {}
+",
+        diff(typed, expected)
+    )
+}
+
+/// Running score for a quiz session.
+#[derive(Debug, Default, Serialize)]
+pub struct Score {
+    correct: usize,
+    incorrect: usize,
+}
+
+impl Score {
+    pub fn record(&mut self, correct: bool) {
+        if correct {
+            self.correct += 1;
+        } else {
+            self.incorrect += 1;
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let total = self.correct + self.incorrect;
+        let percent = (self.correct * 100).checked_div(total).unwrap_or(0);
+
+        format!(
+            "Score: {}/{total} correct ({percent}%), {} incorrect",
+            self.correct, self.incorrect
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let cases = vec![
+            ("Hello", "hello"),
+            ("  hello   world ", "hello world"),
+            ("hello, world!", "hello world"),
+            ("Café", "cafe"),
+            ("über-naïve", "ubernaive"),
+        ];
+
+        for (index, (input, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(normalize(input), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let cases = vec![
+            ("", "", 0),
+            ("abc", "", 3),
+            ("", "abc", 3),
+            ("kitten", "sitting", 3),
+            ("flaw", "lawn", 2),
+        ];
+
+        for (index, (a, b, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(edit_distance(a, b), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_check_answer() {
+        let cases = vec![
+            ("Back", "back", 0, true),
+            ("the cafe.", "The Café", 0, true),
+            ("bakc", "back", 1, false),
+            ("bak", "back", 1, true),
+            ("flashcrads", "flashcards", 2, true),
+            ("flashcrads", "flashcards", 1, false),
+            ("front", "back", 1, false),
+            ("5", "4", 1, false),
+            ("cta", "cat", 2, false),
+        ];
+
+        for (index, (typed, expected, threshold, correct)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                check_answer(typed, expected, threshold).correct,
+                correct,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let cases = vec![
+            ("back", "back", "back"),
+            ("bak", "back", "ba{+c+}k"),
+            ("backs", "back", "back[-s-]"),
+            ("teh", "the", "t[-e-]h{+e+}"),
+            ("cat", "dog", "[-cat-]{+dog+}"),
+        ];
+
+        for (index, (typed, expected, output)) in cases.into_iter().enumerate() {
+            assert_eq!(diff(typed, expected), output, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_score_summary() {
+        let mut score = Score::default();
+        assert_eq!(score.summary(), "Score: 0/0 correct (0%), 0 incorrect");
+
+        score.record(true);
+        score.record(true);
+        score.record(false);
+
+        assert_eq!(score.summary(), "Score: 2/3 correct (66%), 1 incorrect");
+    }
+}
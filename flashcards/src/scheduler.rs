@@ -0,0 +1,216 @@
+use chrono::{Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::Card;
+
+/// The smallest ease factor SM-2 allows a card to drop to.
+const MIN_EASE: f64 = 1.3;
+
+/// Source of "today" for the scheduler, so tests can pin the date.
+pub trait Clock {
+    fn today(&self) -> NaiveDate;
+}
+
+/// Clock backed by the local system time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn today(&self) -> NaiveDate {
+        Local::now().date_naive()
+    }
+}
+
+/// How well the user recalled a card after flipping it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    /// The SM-2 quality score (0-5) this grade maps to.
+    fn quality(self) -> u8 {
+        match self {
+            Grade::Again => 2,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        }
+    }
+}
+
+/// SM-2 scheduling data carried by every card.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    pub ease: f64,
+    pub interval: u32,
+    pub repetitions: u32,
+    /// `None` for cards that have never been reviewed, which are always due.
+    pub due: Option<NaiveDate>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            ease: 2.5,
+            interval: 0,
+            repetitions: 0,
+            due: None,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.due.is_none_or(|due| due <= today)
+    }
+
+    /// Applies one SM-2 review to the schedule.
+    pub fn review(&mut self, grade: Grade, today: NaiveDate) {
+        let quality = f64::from(grade.quality());
+
+        if grade == Grade::Again {
+            // A lapse restarts the repetitions but leaves the ease untouched.
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (f64::from(self.interval) * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+            self.ease += 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02);
+            self.ease = self.ease.max(MIN_EASE);
+        }
+
+        self.due = Some(today + Duration::days(i64::from(self.interval)));
+    }
+}
+
+/// Decides which cards are due and how grading moves them on. SM-2, the
+/// default, is [`Scheduler`]; the Leitner boxes are the alternative.
+pub trait CardScheduler {
+    /// Indexes of the cards that should be presented today, in deck order.
+    fn due_cards(&self, cards: &[Card]) -> Vec<usize>;
+
+    fn grade(&self, card: &mut Card, grade: Grade);
+}
+
+/// Schedules cards with SM-2.
+pub struct Scheduler<C: Clock> {
+    clock: C,
+}
+
+impl<C: Clock> Scheduler<C> {
+    pub fn new(clock: C) -> Self {
+        Self { clock }
+    }
+}
+
+impl<C: Clock> CardScheduler for Scheduler<C> {
+    fn due_cards(&self, cards: &[Card]) -> Vec<usize> {
+        let today = self.clock.today();
+
+        cards
+            .iter()
+            .enumerate()
+            .filter(|(_, card)| card.schedule.is_due(today))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn grade(&self, card: &mut Card, grade: Grade) {
+        card.schedule.review(grade, self.clock.today());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedClock(NaiveDate);
+
+    impl Clock for FixedClock {
+        fn today(&self) -> NaiveDate {
+            self.0
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn card() -> Card {
+        Card::new("front".to_string(), "back".to_string())
+    }
+
+    #[test]
+    fn test_review_intervals() {
+        let today = date(2024, 1, 1);
+        let mut schedule = Schedule::default();
+
+        schedule.review(Grade::Good, today);
+        assert_eq!(schedule.interval, 1);
+        assert_eq!(schedule.due, Some(date(2024, 1, 2)));
+
+        schedule.review(Grade::Good, today);
+        assert_eq!(schedule.interval, 6);
+
+        schedule.review(Grade::Good, today);
+        assert_eq!(schedule.interval, 15);
+        assert_eq!(schedule.due, Some(date(2024, 1, 16)));
+    }
+
+    #[test]
+    fn test_review_ease() {
+        let today = date(2024, 1, 1);
+        let cases = vec![
+            (Grade::Again, 2.5),
+            (Grade::Hard, 2.36),
+            (Grade::Good, 2.5),
+            (Grade::Easy, 2.6),
+        ];
+
+        for (index, (grade, expected)) in cases.into_iter().enumerate() {
+            let mut schedule = Schedule::default();
+            schedule.review(grade, today);
+
+            assert!(
+                (schedule.ease - expected).abs() < 1e-9,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_again_resets_repetitions() {
+        let today = date(2024, 1, 1);
+        let mut schedule = Schedule::default();
+
+        schedule.review(Grade::Easy, today);
+        schedule.review(Grade::Easy, today);
+        schedule.review(Grade::Again, today);
+
+        assert_eq!(schedule.repetitions, 0);
+        assert_eq!(schedule.interval, 1);
+        assert_eq!(schedule.due, Some(date(2024, 1, 2)));
+    }
+
+    #[test]
+    fn test_due_cards() {
+        let scheduler = Scheduler::new(FixedClock(date(2024, 1, 10)));
+        let mut cards = vec![card(), card(), card()];
+
+        cards[1].schedule.due = Some(date(2024, 1, 11));
+        cards[2].schedule.due = Some(date(2024, 1, 10));
+
+        assert_eq!(scheduler.due_cards(&cards), vec![0, 2]);
+
+        scheduler.grade(&mut cards[0], Grade::Good);
+        assert_eq!(scheduler.due_cards(&cards), vec![2]);
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{BufRead, Write};
+use std::time::Instant;
+
+use crate::session::{Event, Frontend, View};
+use crate::{parse_input, screen, Command, Mode};
+
+/// Front-end for sessions run from a file of commands, or from stdin,
+/// without a terminal. Blank lines and lines starting with `#` are skipped,
+/// and the session quits when the commands run out.
+///
+/// Every screen is printed in turn with the command that follows it, so the
+/// output can be compared with a snapshot. With `json` each card shown and
+/// each event is printed as a line of JSON instead.
+pub struct Script<R, W> {
+    commands: R,
+    out: W,
+    json: bool,
+    mode: Mode,
+    options: Vec<usize>,
+    /// The last command read, reported along with what it did.
+    input: String,
+}
+
+impl<R: BufRead, W: Write> Script<R, W> {
+    pub fn new(commands: R, out: W, json: bool) -> Self {
+        Self {
+            commands,
+            out,
+            json,
+            mode: Mode::Study,
+            options: Vec::new(),
+            input: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Script<R, W> {
+    fn show(&mut self, view: &View) -> std::io::Result<()> {
+        self.mode = view.mode;
+        self.options = view.options.to_vec();
+
+        if self.json {
+            let mut value = serde_json::to_value(view)?;
+            value["type"] = "card".into();
+            writeln!(self.out, "{value}")
+        } else {
+            write!(self.out, "{}", screen(view, &view.text))
+        }
+    }
+
+    /// Scripts never run out of time, so that their output stays the same
+    /// from one run to the next.
+    fn read(&mut self, _deadline: Option<Instant>) -> std::io::Result<Command> {
+        loop {
+            self.input.clear();
+            if self.commands.read_line(&mut self.input)? == 0 {
+                return Ok(Command::Quit);
+            }
+
+            let input = self.input.trim();
+            if input.is_empty() || input.starts_with('#') {
+                continue;
+            }
+            if !self.json {
+                writeln!(self.out, "> {input}\n")?;
+            }
+            return Ok(parse_input(input, self.mode, &self.options));
+        }
+    }
+
+    fn event(&mut self, event: &Event) -> std::io::Result<()> {
+        if !self.json {
+            return Ok(());
+        }
+
+        let mut value = serde_json::to_value(event)?;
+        value["input"] = self.input.trim().into();
+        writeln!(self.out, "{value}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Grade;
+
+    fn view(feedback: &str) -> View<'_> {
+        View {
+            mode: Mode::Study,
+            deck: "rust".to_string(),
+            state: "front",
+            text: "What is Rust?".to_string(),
+            options: &[],
+            backs: Vec::new(),
+            count: 1,
+            total: 2,
+            feedback,
+            deadline: None,
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        let mut script = Script::new("f\n\n# a comment\n  3 \n".as_bytes(), Vec::new(), false);
+
+        let cases = vec![
+            Command::Flip,
+            Command::Grade(Grade::Good),
+            Command::Quit,
+            Command::Quit,
+        ];
+        for (index, expected) in cases.into_iter().enumerate() {
+            assert_eq!(script.read(None).unwrap(), expected, "Case {index} failed");
+        }
+        assert_eq!(String::from_utf8(script.out).unwrap(), "> f\n\n> 3\n\n");
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut script = Script::new("n\n".as_bytes(), Vec::new(), false);
+
+        script.show(&view("")).unwrap();
+        script.read(None).unwrap();
+        script.event(&Event::Moved).unwrap();
+
+        let output = String::from_utf8(script.out).unwrap();
+        assert!(output.starts_with("deck: rust\n\nfront: What is Rust?\n\n1/2\n"));
+        assert!(output.ends_with("\n> n\n\n"));
+    }
+
+    #[test]
+    fn test_json() {
+        let mut script = Script::new("3\n".as_bytes(), Vec::new(), true);
+
+        script.show(&view("Order: file\n\n")).unwrap();
+        script.read(None).unwrap();
+        script
+            .event(&Event::Graded {
+                grade: Grade::Good,
+                correct: None,
+            })
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(script.out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                serde_json::json!({
+                    "type": "card",
+                    "mode": "study",
+                    "deck": "rust",
+                    "state": "front",
+                    "text": "What is Rust?",
+                    "options": [],
+                    "count": 1,
+                    "total": 2,
+                    "feedback": "Order: file\n\n",
+                    "attachments": [],
+                }),
+                serde_json::json!({
+                    "type": "graded",
+                    "grade": "good",
+                    "correct": null,
+                    "input": "3",
+                }),
+            ]
+        );
+    }
+}
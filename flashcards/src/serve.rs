@@ -0,0 +1,454 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+
+use serde::Deserialize;
+use serde_json::json;
+use tiny_http::{Header, Method, Response};
+
+use crate::parse_input;
+use crate::session::{Event, Session};
+
+/// The single page UI, served at `/`. It only talks to the JSON API.
+const PAGE: &str = include_str!("serve.html");
+
+/// Body of `POST /answer`: a command or answer, as typed in the line based
+/// interface.
+#[derive(Deserialize)]
+struct Answer {
+    input: String,
+}
+
+/// The request headers the server checks.
+#[derive(Default)]
+struct Headers<'a> {
+    host: Option<&'a str>,
+    origin: Option<&'a str>,
+    content_type: Option<&'a str>,
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: u16, value: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+}
+
+/// A local web server for studying a session in the browser.
+///
+/// `GET /card` returns the current card and the score, and `POST /answer`
+/// applies a command to the session, returning what it did along with the
+/// card that is shown next. Since answers can edit the deck, requests from
+/// other sites or for other hosts are refused, and answers must be JSON.
+pub struct Server {
+    http: tiny_http::Server,
+    session: Session,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, session: Session) -> std::io::Result<Self> {
+        let http = tiny_http::Server::http(addr).map_err(std::io::Error::other)?;
+
+        Ok(Self { http, session })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.http
+            .server_addr()
+            .to_ip()
+            .expect("the server listens on TCP")
+    }
+
+    /// Answers requests until the session is quit or finished, then returns
+    /// the session with its progress saved.
+    pub fn run(mut self) -> std::io::Result<Session> {
+        for mut request in self.http.incoming_requests() {
+            let mut body = String::new();
+            // Bodies that are not UTF-8 are left empty, and so rejected
+            let _ = request.as_reader().read_to_string(&mut body);
+
+            let addr = self.addr();
+            let header = |name: &'static str| {
+                request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv(name))
+                    .map(|header| header.value.as_str())
+            };
+            let headers = Headers {
+                host: header("Host"),
+                origin: header("Origin"),
+                content_type: header("Content-Type"),
+            };
+
+            let (reply, done) = respond(
+                &mut self.session,
+                addr,
+                &headers,
+                request.method(),
+                request.url(),
+                &body,
+            );
+            let header = Header::from_bytes("Content-Type", reply.content_type)
+                .expect("content types are valid header values");
+            request.respond(
+                Response::from_string(reply.body)
+                    .with_status_code(reply.status)
+                    .with_header(header),
+            )?;
+
+            if done {
+                break;
+            }
+        }
+
+        self.session.save()?;
+        Ok(self.session)
+    }
+}
+
+/// Whether `host`, as a `Host` header or the host of an `Origin`, names the
+/// address the server is bound to. Other names are refused, so that pages
+/// on other sites cannot reach the server by pointing their own domain at
+/// it.
+fn is_local(host: &str, addr: SocketAddr) -> bool {
+    let loopback = addr.ip().is_loopback() || addr.ip().is_unspecified();
+    if host == format!("localhost:{}", addr.port()) {
+        return loopback;
+    }
+
+    host.parse::<SocketAddr>().is_ok_and(|host| {
+        host.port() == addr.port() && (addr.ip().is_unspecified() || host.ip() == addr.ip())
+    })
+}
+
+/// Handles a request, returning the reply and whether the session is over.
+fn respond(
+    session: &mut Session,
+    addr: SocketAddr,
+    headers: &Headers,
+    method: &Method,
+    url: &str,
+    body: &str,
+) -> (Reply, bool) {
+    let path = url.split('?').next().unwrap_or(url);
+
+    if !headers.host.is_some_and(|host| is_local(host, addr)) {
+        return (Reply::error(403, "unknown host"), false);
+    }
+    let origin = headers.origin.map(|origin| origin.strip_prefix("http://"));
+    if origin.is_some_and(|host| !host.is_some_and(|host| is_local(host, addr))) {
+        return (
+            Reply::error(403, "requests from other sites are not allowed"),
+            false,
+        );
+    }
+
+    let reply = match (method, path) {
+        (Method::Get, "/") => Reply {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: PAGE.to_string(),
+        },
+        (Method::Get, "/card") => Reply::json(200, state(session, None)),
+        (Method::Post, "/answer") => {
+            // Other sites can send forms and plain text without asking, but
+            // not JSON
+            let json = headers.content_type.is_some_and(|content_type| {
+                content_type.split(';').next().map(str::trim) == Some("application/json")
+            });
+            if !json {
+                return (Reply::error(415, "answers must be sent as JSON"), false);
+            }
+
+            let answer = match serde_json::from_str::<Answer>(body) {
+                Ok(answer) => answer,
+                Err(err) => return (Reply::error(400, &err.to_string()), false),
+            };
+            if session.is_finished() {
+                return (Reply::error(409, "every due card has been reviewed"), false);
+            }
+
+            let command = {
+                let view = session.view();
+                parse_input(answer.input.trim(), view.mode, view.options)
+            };
+            // Failures are the client's to report, and the server keeps
+            // going
+            let event = match session.apply(command) {
+                Ok(event) => event,
+                Err(err) => return (Reply::error(500, &err.to_string()), false),
+            };
+            // Saved as it goes, since the server is usually stopped with
+            // Ctrl-C
+            if matches!(event, Event::Graded { .. } | Event::Edited) {
+                if let Err(err) = session.save() {
+                    let message = format!("the progress could not be saved: {err}");
+                    return (Reply::error(500, &message), false);
+                }
+            }
+
+            let done = event == Event::Quit || session.is_finished();
+            return (Reply::json(200, state(session, Some(event))), done);
+        }
+        (_, "/" | "/card" | "/answer") => Reply::error(405, "method not allowed"),
+        _ => Reply::error(404, "not found"),
+    };
+
+    (reply, false)
+}
+
+/// The JSON for the session: the card shown, if any are left, and the last
+/// event when there is one.
+fn state(session: &Session, event: Option<Event>) -> serde_json::Value {
+    let finished = session.is_finished();
+
+    json!({
+        "event": event,
+        "finished": finished,
+        "card": (!finished).then(|| session.view()),
+        "feedback": session.feedback(),
+        "score": session.score(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::session::tests::session;
+    use crate::Mode;
+
+    /// Where the progress of the test session with this name is saved.
+    fn sidecar(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "flashcards-{}-session-{name}.csv.progress.json",
+            std::process::id()
+        ))
+    }
+
+    const ADDR: &str = "127.0.0.1:8080";
+
+    /// The headers a browser sends along with the page's own requests.
+    fn local() -> Headers<'static> {
+        Headers {
+            host: Some(ADDR),
+            origin: Some("http://127.0.0.1:8080"),
+            content_type: Some("application/json"),
+        }
+    }
+
+    fn post(session: &mut Session, input: &str) -> (u16, serde_json::Value) {
+        let body = json!({ "input": input }).to_string();
+        let (reply, _) = respond(
+            session,
+            ADDR.parse().unwrap(),
+            &local(),
+            &Method::Post,
+            "/answer",
+            &body,
+        );
+
+        (reply.status, serde_json::from_str(&reply.body).unwrap())
+    }
+
+    #[test]
+    fn test_respond() {
+        let mut session = session("serve", Mode::Study, None);
+
+        let cases = vec![
+            (Method::Get, "/", 200, "text/html; charset=utf-8"),
+            (Method::Get, "/card?fresh", 200, "application/json"),
+            (Method::Delete, "/card", 405, "application/json"),
+            (Method::Get, "/cards", 404, "application/json"),
+            (Method::Post, "/answer", 400, "application/json"),
+        ];
+        for (index, (method, url, status, content_type)) in cases.into_iter().enumerate() {
+            let (reply, done) = respond(
+                &mut session,
+                ADDR.parse().unwrap(),
+                &local(),
+                &method,
+                url,
+                "",
+            );
+            assert_eq!(reply.status, status, "Case {index} failed");
+            assert_eq!(reply.content_type, content_type, "Case {index} failed");
+            assert!(!done, "Case {index} failed");
+        }
+
+        // Requests other sites could make, which would edit the deck
+        let body = json!({ "input": "delete" }).to_string();
+        let cases = vec![
+            (
+                Headers {
+                    content_type: Some("text/plain"),
+                    ..local()
+                },
+                415,
+            ),
+            (
+                Headers {
+                    content_type: None,
+                    ..local()
+                },
+                415,
+            ),
+            (
+                Headers {
+                    origin: Some("https://evil.example"),
+                    ..local()
+                },
+                403,
+            ),
+            (
+                Headers {
+                    origin: Some("null"),
+                    ..local()
+                },
+                403,
+            ),
+            (
+                Headers {
+                    host: Some("evil.example:8080"),
+                    origin: None,
+                    ..local()
+                },
+                403,
+            ),
+            (
+                Headers {
+                    host: None,
+                    ..local()
+                },
+                403,
+            ),
+        ];
+        for (index, (headers, status)) in cases.into_iter().enumerate() {
+            let (reply, _) = respond(
+                &mut session,
+                ADDR.parse().unwrap(),
+                &headers,
+                &Method::Post,
+                "/answer",
+                &body,
+            );
+            assert_eq!(reply.status, status, "Case {index} failed");
+        }
+        assert_eq!(session.view().total, 2);
+
+        let (status, value) = post(&mut session, "f");
+        assert_eq!(status, 200);
+        assert_eq!(value["event"], json!({ "type": "flipped" }));
+        assert_eq!(value["card"]["text"], "1");
+
+        post(&mut session, "3");
+        post(&mut session, "f");
+        let (_, value) = post(&mut session, "good");
+        assert_eq!(value["finished"], true);
+        assert_eq!(value["card"], serde_json::Value::Null);
+
+        let (status, _) = post(&mut session, "f");
+        assert_eq!(status, 409);
+        std::fs::remove_file(sidecar("serve")).unwrap();
+    }
+
+    #[test]
+    fn test_respond_save_error() {
+        let mut session = session("unsaved", Mode::Study, None);
+        // Progress cannot be saved over a directory
+        std::fs::create_dir(sidecar("unsaved")).unwrap();
+
+        post(&mut session, "f");
+        let (status, value) = post(&mut session, "3");
+        assert_eq!(status, 500);
+        assert!(value["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("the progress could not be saved"));
+        let (status, _) = post(&mut session, "f");
+        assert_eq!(status, 200);
+
+        std::fs::remove_dir(sidecar("unsaved")).unwrap();
+        let mut tmp = sidecar("unsaved").into_os_string();
+        tmp.push(".tmp");
+        std::fs::remove_file(tmp).unwrap();
+    }
+
+    /// Sends a request over HTTP and returns the body of the response.
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> serde_json::Value {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_is_local() {
+        let cases = vec![
+            ("127.0.0.1:8080", "127.0.0.1:8080", true),
+            ("localhost:8080", "127.0.0.1:8080", true),
+            ("127.0.0.1:9090", "127.0.0.1:8080", false),
+            ("evil.example:8080", "127.0.0.1:8080", false),
+            ("192.168.1.2:8080", "0.0.0.0:8080", true),
+            ("localhost:8080", "192.168.1.2:8080", false),
+        ];
+
+        for (index, (host, addr, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                is_local(host, addr.parse().unwrap()),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_server() {
+        let server = Server::bind("127.0.0.1:0", session("server", Mode::Quiz, None)).unwrap();
+        let addr = server.addr();
+
+        let client = std::thread::spawn(move || {
+            let card = request(addr, "GET", "/card", "");
+            let answer = request(addr, "POST", "/answer", r#"{"input": "1"}"#);
+            let quit = request(addr, "POST", "/answer", r#"{"input": ":q"}"#);
+            (card, answer, quit)
+        });
+        let session = server.run().unwrap();
+        let (card, answer, quit) = client.join().unwrap();
+
+        assert_eq!(card["card"]["text"], "one");
+        assert_eq!(card["card"]["mode"], "quiz");
+        assert_eq!(answer["event"]["correct"], true);
+        assert_eq!(answer["card"]["text"], "two");
+        assert_eq!(quit["event"]["type"], "quit");
+        assert_eq!(
+            session.score().summary(),
+            "Score: 1/1 correct (100%), 0 incorrect"
+        );
+        std::fs::remove_file(sidecar("server")).unwrap();
+    }
+}
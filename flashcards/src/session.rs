@@ -0,0 +1,763 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use rand::rngs::StdRng;
+use serde::Serialize;
+
+use crate::config::Settings;
+use crate::deck::Deck;
+use crate::error::DeckError;
+use crate::media::{self, Attachment, Kind};
+use crate::order::Order;
+use crate::progress::Review;
+use crate::scheduler::{CardScheduler, Grade};
+use crate::{card_label, card_text, choice, direction, quiz, Card, Command, Mode};
+
+/// Longest time a single review counts for, so walking away from a card
+/// does not inflate the time spent.
+const MAX_REVIEW_TIME: Duration = Duration::from_secs(60);
+
+/// What applying a command did to a session.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Another card of the queue is shown.
+    Moved,
+    Flipped,
+    HintShown,
+    /// The queue was put in this order, starting over from its first card.
+    Reordered {
+        order: Order,
+    },
+    /// The card was graded. `correct` is set when the grade came from an
+    /// answer, a choice or running out of time.
+    Graded {
+        grade: Grade,
+        correct: Option<bool>,
+    },
+    /// The deck was edited, or the edit failed; the feedback says which.
+    Edited,
+    /// The command does nothing right now, such as grading a card whose
+    /// answer has not been seen.
+    Ignored,
+    Quit,
+}
+
+/// Everything a front-end needs to show the current card.
+#[derive(Debug, Serialize)]
+pub struct View<'a> {
+    pub mode: Mode,
+    /// The card's deck, with its direction, box and flag.
+    pub deck: String,
+    pub state: &'static str,
+    pub text: String,
+    /// Cards whose backs are the options of a choice session, in order.
+    #[serde(skip)]
+    pub options: &'a [usize],
+    #[serde(rename = "options")]
+    pub backs: Vec<&'a str>,
+    pub count: usize,
+    pub total: usize,
+    /// What the last command did, to show above the card.
+    pub feedback: &'a str,
+    /// When the time for answering a timed card runs out.
+    #[serde(skip)]
+    pub deadline: Option<Instant>,
+    /// The images and sounds of the side shown.
+    pub attachments: Vec<Attachment>,
+}
+
+impl View<'_> {
+    /// Paths of the images attached to the side shown.
+    pub fn images(&self) -> Vec<&Path> {
+        self.attached(Kind::Image)
+    }
+
+    pub fn sounds(&self) -> Vec<&Path> {
+        self.attached(Kind::Sound)
+    }
+
+    fn attached(&self, kind: Kind) -> Vec<&Path> {
+        self.attachments
+            .iter()
+            .filter(|attachment| attachment.kind == kind)
+            .map(|attachment| attachment.path.as_path())
+            .collect()
+    }
+}
+
+/// Shows a session and reads the commands for it.
+pub trait Frontend {
+    fn show(&mut self, view: &View) -> std::io::Result<()>;
+
+    /// The next command, or [`Command::Timeout`] once `deadline` passes.
+    fn read(&mut self, deadline: Option<Instant>) -> std::io::Result<Command>;
+
+    /// Reports what the last command read did. Front-ends that show the
+    /// whole view again after each command can leave this out.
+    fn event(&mut self, _event: &Event) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A study session over a queue of due cards, kept apart from how it is
+/// shown, so that any [`Frontend`] can drive it.
+pub struct Session {
+    mode: Mode,
+    cards: Vec<Card>,
+    queue: Vec<usize>,
+    decks: Vec<Deck>,
+    scheduler: Box<dyn CardScheduler>,
+    rng: StdRng,
+    order: Order,
+    threshold: usize,
+    timed: Option<Duration>,
+    /// Number of Leitner boxes, when scheduling with them.
+    boxes: Option<u32>,
+    current_index: usize,
+    state: &'static str,
+    score: quiz::Score,
+    feedback: String,
+    options: Vec<usize>,
+    options_for: Option<usize>,
+    show_hint: bool,
+    shown_at: Instant,
+    timed_card: Option<usize>,
+    finished: bool,
+}
+
+impl Session {
+    /// Starts a session over `queue`, indexes of `cards` that must not be
+    /// empty.
+    pub fn new(
+        settings: &Settings,
+        decks: Vec<Deck>,
+        cards: Vec<Card>,
+        queue: Vec<usize>,
+        scheduler: Box<dyn CardScheduler>,
+        rng: StdRng,
+    ) -> Self {
+        let mut session = Self {
+            mode: settings.mode,
+            cards,
+            queue,
+            decks,
+            scheduler,
+            rng,
+            order: settings.order,
+            threshold: settings.threshold,
+            timed: settings.timed,
+            boxes: settings
+                .leitner
+                .as_ref()
+                .map(|cadence| cadence.len() as u32),
+            current_index: 0,
+            state: "front",
+            score: quiz::Score::default(),
+            feedback: String::new(),
+            options: Vec::new(),
+            options_for: None,
+            show_hint: false,
+            shown_at: Instant::now(),
+            timed_card: None,
+            finished: false,
+        };
+        session.prepare();
+
+        session
+    }
+
+    /// Whether every card of the queue has been graded or removed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn score(&self) -> &quiz::Score {
+        &self.score
+    }
+
+    pub fn feedback(&self) -> &str {
+        &self.feedback
+    }
+
+    /// When the time for answering the current card runs out. Timed
+    /// sessions only limit the time to answer the front.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.timed
+            .filter(|_| self.state == "front")
+            .map(|limit| self.shown_at + limit)
+    }
+
+    pub fn view(&self) -> View<'_> {
+        let card = &self.cards[self.queue[self.current_index]];
+        // Choice sessions list the options under the front instead
+        let state = if self.mode == Mode::Choice {
+            "front"
+        } else {
+            self.state
+        };
+
+        let deck = &self.decks[card.deck];
+        let text = card_text(card, state, self.show_hint);
+
+        View {
+            mode: self.mode,
+            deck: card_label(&deck.name, card, self.boxes),
+            state: self.state,
+            attachments: media::resolve(&text, deck.path.parent().unwrap_or(Path::new(""))),
+            text,
+            options: &self.options,
+            backs: self
+                .options
+                .iter()
+                .map(|index| self.cards[*index].back())
+                .collect(),
+            count: self.current_index + 1,
+            total: self.queue.len(),
+            feedback: &self.feedback,
+            deadline: self.deadline(),
+        }
+    }
+
+    /// Applies `command` to the current card. Nothing changes once the
+    /// session is finished.
+    pub fn apply(&mut self, command: Command) -> std::io::Result<Event> {
+        if self.finished {
+            return Ok(Event::Ignored);
+        }
+        let card_index = self.queue[self.current_index];
+        self.feedback.clear();
+        self.show_hint = command == Command::Hint;
+
+        // Answers are checked against the back and graded automatically
+        let correct = match &command {
+            Command::Answer(typed) => {
+                let expected = self.cards[card_index].back();
+                let answer = quiz::check_answer(typed, expected, self.threshold);
+                self.feedback = format!("{}\n", quiz::feedback(&answer, typed, expected));
+                Some(answer.correct)
+            }
+            Command::Choose(picked) => {
+                let expected = self.cards[card_index].back();
+                let correct = self.cards[*picked].back() == expected;
+                self.feedback = if correct {
+                    "Correct!\n\n".to_string()
+                } else {
+                    format!("Incorrect. The answer was: {expected}\n\n")
+                };
+                Some(correct)
+            }
+            // Running out of time shows the answer and counts as a miss
+            Command::Timeout => {
+                self.feedback = format!(
+                    "Time's up! The answer was: {}\n\n",
+                    self.cards[card_index].back()
+                );
+                Some(false)
+            }
+            _ => None,
+        };
+        let command = match correct {
+            Some(correct) => {
+                self.score.record(correct);
+                self.state = "back";
+                Command::Grade(if correct { Grade::Good } else { Grade::Again })
+            }
+            None => command,
+        };
+
+        let event = match command {
+            Command::Next if self.current_index + 1 < self.queue.len() => {
+                self.current_index += 1;
+                self.state = "front";
+                Event::Moved
+            }
+            Command::Previous if self.current_index > 0 => {
+                self.current_index -= 1;
+                self.state = "front";
+                Event::Moved
+            }
+            Command::Flip => {
+                self.state = if self.state == "back" {
+                    "front"
+                } else {
+                    "back"
+                };
+                Event::Flipped
+            }
+            Command::Hint => Event::HintShown,
+            Command::Shuffle => {
+                Order::Shuffle.apply(&self.cards, &mut self.queue, &mut self.rng);
+                self.current_index = 0;
+                self.state = "front";
+                Event::Reordered {
+                    order: Order::Shuffle,
+                }
+            }
+            Command::Order(picked) => {
+                self.order = picked.unwrap_or_else(|| self.order.next());
+                self.order
+                    .apply(&self.cards, &mut self.queue, &mut self.rng);
+                self.feedback = format!("Order: {}\n\n", self.order.name());
+                self.current_index = 0;
+                self.state = "front";
+                Event::Reordered { order: self.order }
+            }
+            // Cards can only be graded once their answer has been seen
+            Command::Grade(grade) if self.state == "back" => {
+                grade_card(
+                    &mut self.cards,
+                    &mut self.queue,
+                    self.current_index,
+                    grade,
+                    self.scheduler.as_ref(),
+                    &mut self.decks,
+                    self.shown_at.elapsed(),
+                )?;
+                // A lapsed card coming round again is timed afresh
+                self.timed_card = None;
+                if self.current_index >= self.queue.len() {
+                    self.current_index = 0;
+                }
+                self.state = "front";
+                Event::Graded { grade, correct }
+            }
+            Command::EditFront(_)
+            | Command::EditBack(_)
+            | Command::Add(_, _)
+            | Command::Delete
+            | Command::Suspend
+            | Command::Flag => {
+                self.feedback = match edit_card(
+                    &mut self.cards,
+                    &mut self.queue,
+                    &mut self.current_index,
+                    &mut self.decks,
+                    &command,
+                ) {
+                    Ok(feedback) => feedback,
+                    Err(err) => format!("{err}\n\n"),
+                };
+                if matches!(command, Command::Delete | Command::Suspend) {
+                    self.state = "front";
+                }
+                Event::Edited
+            }
+            Command::Quit => Event::Quit,
+            _ => Event::Ignored,
+        };
+
+        self.finished = self.queue.is_empty();
+        if !self.finished {
+            self.prepare();
+        }
+
+        Ok(event)
+    }
+
+    /// Saves the progress of every deck with cards in the session. Decks
+    /// that were filtered out of it are left untouched.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        for (index, deck) in self.decks.iter_mut().enumerate() {
+            if self.cards.iter().any(|card| card.deck == index) {
+                deck.store.save()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts the timer and draws the options for the current card, when
+    /// it has changed.
+    fn prepare(&mut self) {
+        let card_index = self.queue[self.current_index];
+
+        // The time spent on a card is measured from when it first shows up
+        if self.timed_card != Some(card_index) {
+            self.shown_at = Instant::now();
+            self.timed_card = Some(card_index);
+        }
+
+        // Options are only drawn once per card so they stay put between inputs
+        if self.mode == Mode::Choice && self.options_for != Some(card_index) {
+            self.options = choice::options(&self.cards, card_index, &mut self.rng);
+            self.options_for = Some(card_index);
+        }
+    }
+}
+
+/// Grades the card at `current_index` of the queue and takes it out of the
+/// queue, putting it back at the end when it needs to be seen again.
+fn grade_card(
+    cards: &mut [Card],
+    queue: &mut Vec<usize>,
+    current_index: usize,
+    grade: Grade,
+    scheduler: &dyn CardScheduler,
+    decks: &mut [Deck],
+    elapsed: Duration,
+) -> std::io::Result<()> {
+    let now = Utc::now();
+    let card_index = queue.remove(current_index);
+    let card = &mut cards[card_index];
+    let millis = elapsed.min(MAX_REVIEW_TIME).as_millis() as u64;
+    scheduler.grade(card, grade);
+    card.stats.record(grade, now, millis);
+
+    let store = &mut decks[card.deck].store;
+    store.log(Review {
+        card: card.id(),
+        at: now,
+        grade,
+        millis,
+    });
+    store.update(card)?;
+
+    // Lapsed cards are repeated again later in the session
+    if grade == Grade::Again {
+        queue.push(card_index);
+    }
+
+    Ok(())
+}
+
+/// Applies one of the editing commands to the card at `current_index` of
+/// the queue, and writes the card's deck back to its file. Returns the
+/// feedback to show.
+fn edit_card(
+    cards: &mut Vec<Card>,
+    queue: &mut Vec<usize>,
+    current_index: &mut usize,
+    decks: &mut [Deck],
+    command: &Command,
+) -> Result<String, DeckError> {
+    let card_index = queue[*current_index];
+    let (deck_index, row) = (cards[card_index].deck, cards[card_index].row);
+    let same_row = |card: &Card| card.deck == deck_index && card.row == row;
+    let deck = &mut decks[deck_index];
+    if !deck.editable() {
+        return Ok(format!(
+            "{} is a Markdown or Anki deck, which can only be edited in its file.\n\n",
+            deck.name
+        ));
+    }
+    let Some(raw) = deck.rows[row].as_mut() else {
+        return Ok(String::new());
+    };
+
+    let feedback = match command {
+        Command::EditFront(text) | Command::EditBack(text) => {
+            let card = &cards[card_index];
+            if card.cloze.is_some() {
+                return Ok("Cloze cards can only be edited in the deck file.\n\n".to_string());
+            }
+
+            // The front of a turned-around card is the back of its row
+            if matches!(command, Command::EditFront(_)) != card.reversed {
+                raw.front = text.clone();
+            } else {
+                raw.back = text.clone();
+            }
+            let mut edited = if card.reversed {
+                direction::reversed(raw.clone())
+            } else {
+                raw.clone()
+            };
+            edited.schedule = card.schedule.clone();
+            edited.stats = card.stats.clone();
+            edited.leitner = card.leitner.clone();
+            cards[card_index] = edited;
+
+            // Cards without an id are known by their text, so their
+            // progress moves over to the edited text
+            deck.store.update(&cards[card_index])?;
+            "Card updated."
+        }
+        Command::Add(front, back) => {
+            let mut card = Card::new(front.clone(), back.clone());
+            card.deck = deck_index;
+            card.row = deck.rows.len();
+            deck.rows.push(Some(card.clone()));
+            cards.push(card);
+            queue.push(cards.len() - 1);
+            "Card added."
+        }
+        Command::Delete | Command::Suspend => {
+            let feedback = if *command == Command::Delete {
+                deck.rows[row] = None;
+                "Card deleted."
+            } else {
+                raw.suspended = true;
+                "Card suspended."
+            };
+
+            // Every card made from the row goes, both ways round and for
+            // each cloze deletion
+            let before = queue[..*current_index]
+                .iter()
+                .filter(|index| same_row(&cards[**index]))
+                .count();
+            queue.retain(|index| !same_row(&cards[*index]));
+            *current_index -= before;
+            if *current_index >= queue.len() {
+                *current_index = 0;
+            }
+            feedback
+        }
+        Command::Flag => {
+            raw.flagged = !raw.flagged;
+            let flagged = raw.flagged;
+            for card in cards.iter_mut().filter(|card| same_row(card)) {
+                card.flagged = flagged;
+            }
+            if flagged {
+                "Card flagged."
+            } else {
+                "Card unflagged."
+            }
+        }
+        _ => return Ok(String::new()),
+    };
+    deck.write()?;
+
+    Ok(format!("{feedback}\n\n"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::direction::Direction;
+    use crate::format;
+    use crate::media::Graphics;
+    use crate::scheduler::{Scheduler, SystemClock};
+
+    /// Settings for a plain line based session in file order.
+    pub(crate) fn settings(mode: Mode, timed: Option<Duration>) -> Settings {
+        Settings {
+            mode,
+            direction: Direction::FrontToBack,
+            order: Order::File,
+            leitner: None,
+            timed,
+            seed: None,
+            limit: None,
+            threshold: quiz::DEFAULT_THRESHOLD,
+            line: true,
+            plain: true,
+            images: Graphics::Text,
+            audio_command: None,
+        }
+    }
+
+    /// A session over two new cards, "one" and "two", of a deck in the
+    /// temp directory.
+    pub(crate) fn session(name: &str, mode: Mode, timed: Option<Duration>) -> Session {
+        let path = std::env::temp_dir().join(format!(
+            "flashcards-{}-session-{name}.csv",
+            std::process::id()
+        ));
+        let cards = vec![
+            Card::new("one".to_string(), "1".to_string()),
+            Card::new("two".to_string(), "2".to_string()),
+        ];
+        Session::new(
+            &settings(mode, timed),
+            vec![Deck::new(path).unwrap()],
+            cards,
+            vec![0, 1],
+            Box::new(Scheduler::new(SystemClock)),
+            StdRng::seed_from_u64(1),
+        )
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut session = session("apply", Mode::Study, None);
+
+        let cases = vec![
+            (Command::Previous, Event::Ignored, "one", "front"),
+            (Command::Next, Event::Moved, "two", "front"),
+            (Command::Next, Event::Ignored, "two", "front"),
+            (Command::Previous, Event::Moved, "one", "front"),
+            (Command::Grade(Grade::Good), Event::Ignored, "one", "front"),
+            (Command::Flip, Event::Flipped, "1", "back"),
+            (
+                Command::Grade(Grade::Again),
+                Event::Graded {
+                    grade: Grade::Again,
+                    correct: None,
+                },
+                "two",
+                "front",
+            ),
+            (Command::Flip, Event::Flipped, "2", "back"),
+            (
+                Command::Grade(Grade::Good),
+                Event::Graded {
+                    grade: Grade::Good,
+                    correct: None,
+                },
+                "one",
+                "front",
+            ),
+            (Command::Unknown, Event::Ignored, "one", "front"),
+        ];
+
+        for (index, (command, event, text, state)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                session.apply(command).unwrap(),
+                event,
+                "Case {index} failed"
+            );
+            let view = session.view();
+            assert_eq!(view.text, text, "Case {index} failed");
+            assert_eq!(view.state, state, "Case {index} failed");
+        }
+
+        // The lapsed card came round again and is the last one left
+        assert_eq!(session.view().total, 1);
+        session.apply(Command::Flip).unwrap();
+        session.apply(Command::Grade(Grade::Easy)).unwrap();
+        assert!(session.is_finished());
+        assert_eq!(session.apply(Command::Next).unwrap(), Event::Ignored);
+    }
+
+    #[test]
+    fn test_apply_answers() {
+        let mut session = session("answers", Mode::Quiz, Some(Duration::from_secs(10)));
+        assert!(session.deadline().is_some());
+
+        let event = session.apply(Command::Answer("1".to_string())).unwrap();
+        assert_eq!(
+            event,
+            Event::Graded {
+                grade: Grade::Good,
+                correct: Some(true),
+            }
+        );
+
+        let event = session.apply(Command::Timeout).unwrap();
+        assert_eq!(
+            event,
+            Event::Graded {
+                grade: Grade::Again,
+                correct: Some(false),
+            }
+        );
+        assert_eq!(session.feedback(), "Time's up! The answer was: 2\n\n");
+        assert_eq!(
+            session.score().summary(),
+            "Score: 1/2 correct (50%), 1 incorrect"
+        );
+    }
+
+    #[test]
+    fn test_edit_card() {
+        let dir = std::env::temp_dir().join(format!("flashcards-{}-edit", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rust.csv");
+        std::fs::write(&path, "source,back,front\nbook,b1,f1\nweb,b2,f2\n").unwrap();
+
+        let mut deck = Deck::new(path.clone()).unwrap();
+        let mut cards = format::load_deck(&path, None, &[]).unwrap();
+        for (row, card) in cards.iter_mut().enumerate() {
+            card.row = row;
+        }
+        deck.rows = cards.iter().cloned().map(Some).collect();
+        cards.push(direction::reversed(cards[1].clone()));
+        let mut decks = vec![deck];
+        let mut queue = vec![0, 1, 2];
+        let mut current_index = 0;
+
+        let cases = vec![
+            (
+                Command::EditBack("Fixed".to_string()),
+                "Card updated.\n\n",
+                "source,back,front\nbook,Fixed,f1\nweb,b2,f2\n",
+            ),
+            (
+                Command::Flag,
+                "Card flagged.\n\n",
+                "source,back,front,flagged\nbook,Fixed,f1,true\nweb,b2,f2,\n",
+            ),
+            (
+                Command::Add("f3".to_string(), "b3".to_string()),
+                "Card added.\n\n",
+                "source,back,front,flagged\nbook,Fixed,f1,true\nweb,b2,f2,\n,b3,f3,\n",
+            ),
+        ];
+        for (index, (command, feedback, file)) in cases.into_iter().enumerate() {
+            let result = edit_card(
+                &mut cards,
+                &mut queue,
+                &mut current_index,
+                &mut decks,
+                &command,
+            );
+            assert_eq!(result.unwrap(), feedback, "Case {index} failed");
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                file,
+                "Case {index} failed"
+            );
+        }
+        assert_eq!(cards[0].back(), "Fixed");
+        assert_eq!(queue, vec![0, 1, 2, 3]);
+
+        // Deleting a card also takes its turned-around copy out of the queue
+        current_index = 1;
+        edit_card(
+            &mut cards,
+            &mut queue,
+            &mut current_index,
+            &mut decks,
+            &Command::Delete,
+        )
+        .unwrap();
+        assert_eq!(queue, vec![0, 3]);
+        assert_eq!(current_index, 1);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "source,back,front,flagged\nbook,Fixed,f1,true\n,b3,f3,\n"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_edit_card_markdown() {
+        let path =
+            std::env::temp_dir().join(format!("flashcards-{}-edit-notes.md", std::process::id()));
+        let data = "# Notes\n\nIntro.\n\n## f1\n\nb1\n";
+        std::fs::write(&path, data).unwrap();
+
+        let mut decks = vec![Deck::new(path.clone()).unwrap()];
+        let cards = format::load_deck(&path, None, &[]).unwrap();
+        decks[0].rows = cards.iter().cloned().map(Some).collect();
+
+        for (index, command) in [Command::Suspend, Command::Flag].iter().enumerate() {
+            let feedback = edit_card(
+                &mut cards.clone(),
+                &mut vec![0],
+                &mut 0,
+                &mut decks,
+                command,
+            )
+            .unwrap();
+            assert!(
+                feedback.ends_with("can only be edited in its file.\n\n"),
+                "Case {index} failed"
+            );
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                data,
+                "Case {index} failed"
+            );
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,556 @@
+use std::collections::BTreeMap;
+
+use chrono::{Days, Local, NaiveDate};
+use serde::Serialize;
+
+use crate::progress::Review;
+use crate::scheduler::Grade;
+use crate::Card;
+
+/// Days ahead covered by the due forecast.
+const FORECAST_DAYS: u64 = 30;
+
+/// Days of history shown in the human readable report. `--json` has all of
+/// them.
+const RECENT_DAYS: u64 = 14;
+
+/// How many of the hardest and of the slowest cards are listed.
+const HARDEST_COUNT: usize = 5;
+
+/// Totals shown by `flashcards stats` for one deck.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct DeckStats {
+    pub cards: usize,
+    /// Cards that have never been reviewed.
+    pub new: usize,
+    pub due: usize,
+    pub correct: u32,
+    pub incorrect: u32,
+}
+
+impl DeckStats {
+    pub fn new<'a>(cards: impl IntoIterator<Item = &'a Card>, today: NaiveDate) -> Self {
+        let mut stats = DeckStats::default();
+
+        for card in cards {
+            stats.cards += 1;
+            if card.stats.last_seen.is_none() {
+                stats.new += 1;
+            }
+            if card.schedule.is_due(today) {
+                stats.due += 1;
+            }
+            stats.correct += card.stats.correct;
+            stats.incorrect += card.stats.incorrect;
+        }
+
+        stats
+    }
+
+    pub fn reviews(&self) -> u32 {
+        self.correct + self.incorrect
+    }
+
+    /// Share of reviews that were not graded "again", as a percentage.
+    pub fn accuracy(&self) -> Option<u32> {
+        (self.correct * 100).checked_div(self.reviews())
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DeckRow {
+    pub name: String,
+    #[serde(flatten)]
+    pub stats: DeckStats,
+}
+
+/// Reviews logged on one day.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub reviews: u32,
+    pub correct: u32,
+    pub millis: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct HardCard {
+    pub deck: String,
+    pub front: String,
+    pub correct: u32,
+    pub incorrect: u32,
+    /// Percentage of reviews that were not graded "again".
+    pub success_rate: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SlowCard {
+    pub deck: String,
+    pub front: String,
+    /// Mean response time over the timed reviews.
+    pub average_millis: u64,
+    pub reviews: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Forecast {
+    pub date: NaiveDate,
+    pub due: usize,
+}
+
+/// Everything `flashcards stats` reports, built from the cards' progress
+/// and the review log.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub decks: Vec<DeckRow>,
+    /// Days with at least one review, oldest first.
+    pub days: Vec<Day>,
+    pub reviews: u32,
+    pub correct: u32,
+    pub accuracy: Option<u32>,
+    /// Days in a row with reviews, up to today or yesterday.
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub millis: u64,
+    pub hardest: Vec<HardCard>,
+    pub slowest: Vec<SlowCard>,
+    /// Cards due on each of the coming days; overdue cards count as due
+    /// today.
+    pub forecast: Vec<Forecast>,
+}
+
+impl Report {
+    /// `cards` are paired with the name of their deck. Review times are
+    /// bucketed into days in the local time zone.
+    pub fn new(
+        decks: Vec<DeckRow>,
+        cards: &[(&str, &Card)],
+        reviews: &[Review],
+        today: NaiveDate,
+    ) -> Self {
+        let mut days: BTreeMap<NaiveDate, Day> = BTreeMap::new();
+        for review in reviews {
+            let date = review.at.with_timezone(&Local).date_naive();
+            let day = days.entry(date).or_insert_with(|| Day {
+                date,
+                ..Day::default()
+            });
+
+            day.reviews += 1;
+            if review.grade != Grade::Again {
+                day.correct += 1;
+            }
+            day.millis += review.millis;
+        }
+        let days: Vec<Day> = days.into_values().collect();
+
+        let reviews = days.iter().map(|day| day.reviews).sum();
+        let correct = days.iter().map(|day| day.correct).sum();
+        let dates: Vec<NaiveDate> = days.iter().map(|day| day.date).collect();
+        let (current_streak, longest_streak) = streaks(&dates, today);
+
+        Report {
+            decks,
+            reviews,
+            correct,
+            accuracy: (correct * 100).checked_div(reviews),
+            current_streak,
+            longest_streak,
+            millis: days.iter().map(|day| day.millis).sum(),
+            days,
+            hardest: hardest(cards),
+            slowest: slowest(cards),
+            forecast: forecast(cards, today),
+        }
+    }
+
+    /// The report as text for the terminal.
+    pub fn render(&self, today: NaiveDate) -> String {
+        let mut text = table(&self.decks);
+
+        let accuracy = self
+            .accuracy
+            .map_or(String::new(), |accuracy| format!(", {accuracy}%"));
+        text.push_str(&format!(
+            "\nreviews: {} ({} correct{accuracy})\ntime spent: {}\nstreak: {} days (longest {})\n",
+            self.reviews,
+            self.correct,
+            format_duration(self.millis),
+            self.current_streak,
+            self.longest_streak,
+        ));
+
+        let since = today - Days::new(RECENT_DAYS - 1);
+        let recent: Vec<&Day> = self.days.iter().filter(|day| day.date >= since).collect();
+        if !recent.is_empty() {
+            text.push_str(&format!("\nreviews in the last {RECENT_DAYS} days:\n"));
+            for day in recent {
+                text.push_str(&format!(
+                    "  {}  {:>4} reviews  {:>4} correct  {:>8}\n",
+                    day.date,
+                    day.reviews,
+                    day.correct,
+                    format_duration(day.millis)
+                ));
+            }
+        }
+
+        if !self.hardest.is_empty() {
+            text.push_str("\nhardest cards:\n");
+            for card in &self.hardest {
+                text.push_str(&format!(
+                    "  {:>3}%  {} ({}, {} of {} correct)\n",
+                    card.success_rate,
+                    card.front,
+                    card.deck,
+                    card.correct,
+                    card.correct + card.incorrect
+                ));
+            }
+        }
+
+        if !self.slowest.is_empty() {
+            text.push_str("\nslowest cards:\n");
+            for card in &self.slowest {
+                text.push_str(&format!(
+                    "  {:>5.1}s  {} ({}, {} timed reviews)\n",
+                    card.average_millis as f64 / 1000.0,
+                    card.front,
+                    card.deck,
+                    card.reviews
+                ));
+            }
+        }
+
+        let due: Vec<&Forecast> = self.forecast.iter().filter(|day| day.due > 0).collect();
+        if !due.is_empty() {
+            text.push_str(&format!("\ndue in the next {FORECAST_DAYS} days:\n"));
+            for day in due {
+                text.push_str(&format!("  {}  {:>4}\n", day.date, day.due));
+            }
+        }
+
+        text
+    }
+}
+
+/// The current and longest runs of consecutive days in `dates`, which are
+/// sorted. The current run may end yesterday, since today is not over yet.
+fn streaks(dates: &[NaiveDate], today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for &date in dates {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(date) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(date);
+    }
+
+    let current = match previous {
+        Some(last) if last == today || last.succ_opt() == Some(today) => run,
+        _ => 0,
+    };
+
+    (current, longest)
+}
+
+/// Cards that were answered wrong at least once, lowest success rate first.
+fn hardest(cards: &[(&str, &Card)]) -> Vec<HardCard> {
+    let mut hardest: Vec<HardCard> = cards
+        .iter()
+        .filter(|(_, card)| card.stats.incorrect > 0)
+        .map(|(deck, card)| {
+            let (correct, incorrect) = (card.stats.correct, card.stats.incorrect);
+
+            HardCard {
+                deck: deck.to_string(),
+                front: card.front().to_string(),
+                correct,
+                incorrect,
+                success_rate: correct * 100 / (correct + incorrect),
+            }
+        })
+        .collect();
+
+    // Among equally hard cards, the ones seen most often are the surest
+    hardest.sort_by_key(|card| (card.success_rate, u32::MAX - card.incorrect));
+    hardest.truncate(HARDEST_COUNT);
+
+    hardest
+}
+
+/// Cards with timed reviews, longest average response time first.
+fn slowest(cards: &[(&str, &Card)]) -> Vec<SlowCard> {
+    let mut slowest: Vec<SlowCard> = cards
+        .iter()
+        .filter_map(|(deck, card)| {
+            Some(SlowCard {
+                deck: deck.to_string(),
+                front: card.front().to_string(),
+                average_millis: card.stats.average_millis()?,
+                reviews: card.stats.timed,
+            })
+        })
+        .collect();
+
+    slowest.sort_by_key(|card| std::cmp::Reverse(card.average_millis));
+    slowest.truncate(HARDEST_COUNT);
+
+    slowest
+}
+
+fn forecast(cards: &[(&str, &Card)], today: NaiveDate) -> Vec<Forecast> {
+    (0..FORECAST_DAYS)
+        .map(|offset| {
+            let date = today + Days::new(offset);
+            let due = cards
+                .iter()
+                .filter(|(_, card)| {
+                    if offset == 0 {
+                        card.schedule.is_due(today)
+                    } else {
+                        card.schedule.due == Some(date)
+                    }
+                })
+                .count();
+
+            Forecast { date, due }
+        })
+        .collect()
+}
+
+/// Renders a duration as `1h 05m`, `4m 10s` or `12s`.
+fn format_duration(millis: u64) -> String {
+    let seconds = millis / 1000;
+
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, seconds) => format!("{seconds}s"),
+        (0, minutes, seconds) => format!("{minutes}m {seconds:02}s"),
+        (hours, minutes, _) => format!("{hours}h {minutes:02}m"),
+    }
+}
+
+/// Lays the stats of each deck out as a table, one row per deck.
+pub fn table(rows: &[DeckRow]) -> String {
+    let width = rows
+        .iter()
+        .map(|row| row.name.chars().count())
+        .chain(["deck".len()])
+        .max()
+        .unwrap_or(0);
+
+    let mut table = format!(
+        "{:<width$}  {:>6}  {:>6}  {:>6}  {:>8}  {:>8}\n",
+        "deck", "cards", "new", "due", "reviews", "accuracy"
+    );
+    for DeckRow { name, stats } in rows {
+        let accuracy = stats
+            .accuracy()
+            .map_or("-".to_string(), |accuracy| format!("{accuracy}%"));
+
+        table.push_str(&format!(
+            "{name:<width$}  {:>6}  {:>6}  {:>6}  {:>8}  {accuracy:>8}\n",
+            stats.cards,
+            stats.new,
+            stats.due,
+            stats.reviews()
+        ));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn review(card: &str, day: u32, grade: Grade, millis: u64) -> Review {
+        let at = Local
+            .from_local_datetime(&date(day).and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Utc);
+
+        Review {
+            card: card.to_string(),
+            at,
+            grade,
+            millis,
+        }
+    }
+
+    #[test]
+    fn test_deck_stats() {
+        let today = date(10);
+        let mut cards: Vec<Card> = (0..3)
+            .map(|index| Card::new(format!("front {index}"), "back".to_string()))
+            .collect();
+        cards[0].stats.last_seen = Some(Utc::now());
+        cards[0].stats.correct = 3;
+        cards[0].stats.incorrect = 1;
+        cards[0].schedule.due = Some(date(12));
+
+        let stats = DeckStats::new(&cards, today);
+
+        assert_eq!(
+            stats,
+            DeckStats {
+                cards: 3,
+                new: 2,
+                due: 2,
+                correct: 3,
+                incorrect: 1,
+            }
+        );
+        assert_eq!(stats.accuracy(), Some(75));
+        assert_eq!(DeckStats::default().accuracy(), None);
+    }
+
+    #[test]
+    fn test_table() {
+        let rows = vec![
+            DeckRow {
+                name: "rust-traits".to_string(),
+                stats: DeckStats {
+                    cards: 10,
+                    new: 4,
+                    due: 3,
+                    correct: 9,
+                    incorrect: 3,
+                },
+            },
+            DeckRow {
+                name: "go".to_string(),
+                stats: DeckStats::default(),
+            },
+        ];
+
+        assert_eq!(
+            table(&rows),
+            "\
+deck          cards     new     due   reviews  accuracy
+rust-traits      10       4       3        12       75%
+go                0       0       0         0         -
+"
+        );
+    }
+
+    #[test]
+    fn test_streaks() {
+        let cases = vec![
+            (vec![], (0, 0)),
+            (vec![date(10)], (1, 1)),
+            (vec![date(8), date(9)], (2, 2)),
+            (vec![date(1), date(2), date(3), date(9), date(10)], (2, 3)),
+            (vec![date(1), date(2), date(3)], (0, 3)),
+        ];
+
+        for (index, (dates, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(streaks(&dates, date(10)), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        let cases = vec![
+            (0, "0s"),
+            (12_400, "12s"),
+            (250_000, "4m 10s"),
+            (3_900_000, "1h 05m"),
+        ];
+
+        for (index, (millis, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(format_duration(millis), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_report() {
+        let today = date(10);
+        let mut easy = Card::new("easy".to_string(), "back".to_string());
+        easy.stats.correct = 4;
+        easy.schedule.due = Some(date(15));
+        let mut hard = Card::new("hard".to_string(), "back".to_string());
+        hard.stats.correct = 1;
+        hard.stats.incorrect = 3;
+        hard.schedule.due = Some(date(2));
+        (hard.stats.timed, hard.stats.millis) = (2, 6000);
+        let mut harder = Card::new("harder".to_string(), "back".to_string());
+        harder.stats.incorrect = 1;
+        harder.schedule.due = Some(date(11));
+        (harder.stats.timed, harder.stats.millis) = (1, 3000);
+
+        let reviews = vec![
+            review("hard", 8, Grade::Again, 4000),
+            review("hard", 9, Grade::Good, 2000),
+            review("easy", 9, Grade::Easy, 1000),
+            review("harder", 10, Grade::Again, 3000),
+        ];
+        let cards = [("rust", &easy), ("rust", &hard), ("go", &harder)];
+
+        let report = Report::new(Vec::new(), &cards, &reviews, today);
+
+        assert_eq!(
+            report.days,
+            vec![
+                Day {
+                    date: date(8),
+                    reviews: 1,
+                    correct: 0,
+                    millis: 4000,
+                },
+                Day {
+                    date: date(9),
+                    reviews: 2,
+                    correct: 2,
+                    millis: 3000,
+                },
+                Day {
+                    date: date(10),
+                    reviews: 1,
+                    correct: 0,
+                    millis: 3000,
+                },
+            ]
+        );
+        assert_eq!((report.reviews, report.correct), (4, 2));
+        assert_eq!(report.accuracy, Some(50));
+        assert_eq!((report.current_streak, report.longest_streak), (3, 3));
+        assert_eq!(report.millis, 10_000);
+
+        let hardest: Vec<(&str, u32)> = report
+            .hardest
+            .iter()
+            .map(|card| (card.front.as_str(), card.success_rate))
+            .collect();
+        assert_eq!(hardest, vec![("harder", 0), ("hard", 25)]);
+
+        let slowest: Vec<(&str, u64)> = report
+            .slowest
+            .iter()
+            .map(|card| (card.front.as_str(), card.average_millis))
+            .collect();
+        assert_eq!(slowest, vec![("hard", 3000), ("harder", 3000)]);
+
+        assert_eq!(report.forecast.len(), 30);
+        assert_eq!(report.forecast[0].due, 1);
+        assert_eq!(report.forecast[1].due, 1);
+        assert_eq!(report.forecast[5].due, 1);
+        assert_eq!(report.forecast.iter().map(|day| day.due).sum::<usize>(), 3);
+
+        let text = report.render(today);
+        assert!(text.contains("reviews: 4 (2 correct, 50%)\ntime spent: 10s\nstreak: 3 days"));
+        assert!(text.contains("  2024-03-09     2 reviews     2 correct        3s\n"));
+        assert!(text.contains("    0%  harder (go, 0 of 1 correct)\n"));
+        assert!(text.contains("    3.0s  hard (rust, 2 timed reviews)\n"));
+        assert!(text.contains("  2024-03-15     1\n"));
+    }
+}
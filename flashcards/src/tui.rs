@@ -0,0 +1,388 @@
+use std::io::{Stdout, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+
+use crate::markdown::{self, Line};
+use crate::media::{Graphics, Images, IMAGE_ROWS};
+use crate::session::{Frontend, View};
+use crate::{parse_command, Command};
+
+/// Widest the card text is allowed to get, even on very wide terminals.
+const MAX_TEXT_WIDTH: usize = 60;
+
+/// Full-screen, raw-mode terminal front-end for study sessions.
+///
+/// The terminal is restored when this is dropped, including on panics and
+/// early returns.
+pub struct Terminal {
+    out: Stdout,
+    /// Whether card text is rendered as Markdown.
+    markdown: bool,
+    images: Images,
+}
+
+impl Terminal {
+    pub fn enter(markdown: bool, graphics: Graphics) -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        // Built before anything else can fail, so that dropping it on an
+        // error leaves raw mode again
+        let mut terminal = Self {
+            out: std::io::stdout(),
+            markdown,
+            images: Images::new(graphics),
+        };
+        execute!(terminal.out, EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(terminal)
+    }
+
+    /// Draws one frame: the card text centered on screen with its images
+    /// under it, a progress bar below them and a status bar on the last row.
+    pub fn draw(
+        &mut self,
+        deck: &str,
+        state: &str,
+        text: &str,
+        images: &[&Path],
+        count: usize,
+        total: usize,
+    ) -> std::io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (usize::from(width), usize::from(height));
+
+        queue!(
+            self.out,
+            terminal::Clear(ClearType::All),
+            Print(self.images.clear())
+        )?;
+
+        let text_width = width.saturating_sub(4).clamp(1, MAX_TEXT_WIDTH);
+        let lines = if self.markdown {
+            markdown::render(text, text_width)
+        } else {
+            wrap(text, text_width)
+                .into_iter()
+                .map(|text| Line {
+                    width: text.chars().count(),
+                    text,
+                })
+                .collect()
+        };
+
+        // Rendered lines are indented as lists and code need, so they are
+        // centered as a block rather than one by one
+        let block = lines.iter().map(|line| line.width).max().unwrap_or(0);
+        let images: Vec<String> = images
+            .iter()
+            .filter_map(|path| self.images.draw(path).map(str::to_string))
+            .collect();
+        let image_rows = images.len() * (IMAGE_ROWS + 1);
+        let top = height.saturating_sub(lines.len() + image_rows + 4) / 2;
+        for (row, line) in lines.iter().enumerate() {
+            let line_width = if self.markdown { block } else { line.width };
+            let column = width.saturating_sub(line_width) / 2;
+            queue!(
+                self.out,
+                cursor::MoveTo(to_u16(column), to_u16(top + row)),
+                Print(&line.text)
+            )?;
+        }
+        // Cells are about twice as tall as wide, so a square image takes
+        // twice its rows in columns
+        let column = width.saturating_sub(IMAGE_ROWS * 2) / 2;
+        for (index, image) in images.iter().enumerate() {
+            let row = top + lines.len() + 1 + index * (IMAGE_ROWS + 1);
+            queue!(
+                self.out,
+                cursor::MoveTo(to_u16(column), to_u16(row)),
+                Print(image)
+            )?;
+        }
+
+        let bar = progress_bar(count, total, width.saturating_sub(20).min(40));
+        self.print_centered(height.saturating_sub(3), width, &bar)?;
+
+        let status = status_bar(deck, state, width);
+        queue!(
+            self.out,
+            cursor::MoveTo(0, to_u16(height.saturating_sub(1))),
+            SetAttribute(Attribute::Reverse),
+            Print(status),
+            SetAttribute(Attribute::Reset),
+        )?;
+
+        self.out.flush()
+    }
+
+    fn print_centered(&mut self, row: usize, width: usize, line: &str) -> std::io::Result<()> {
+        let column = width.saturating_sub(line.chars().count()) / 2;
+
+        queue!(
+            self.out,
+            cursor::MoveTo(to_u16(column), to_u16(row)),
+            Print(line)
+        )
+    }
+
+    /// Blocks until a key is pressed and returns the matching command.
+    /// `:` opens a prompt for the commands that take text, such as edits.
+    ///
+    /// With a `deadline`, the time left is counted down at the top of the
+    /// screen and `Command::Timeout` is returned once it passes. Resizing
+    /// the terminal returns `Command::Unknown` so the caller redraws.
+    pub fn read_command(&mut self, deadline: Option<Instant>) -> std::io::Result<Command> {
+        loop {
+            if let Some(deadline) = deadline {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Ok(Command::Timeout);
+                }
+
+                let (width, _) = terminal::size()?;
+                let countdown = format!(" {}s left ", seconds_left(left));
+                self.print_centered(0, usize::from(width), &countdown)?;
+                self.out.flush()?;
+
+                // Wake up often enough to keep the countdown current
+                if !event::poll(left.min(Duration::from_millis(200)))? {
+                    continue;
+                }
+            }
+
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if key.code == KeyCode::Char(':') {
+                        return Ok(self
+                            .prompt()?
+                            .map_or(Command::Unknown, |line| parse_command(line.trim())));
+                    }
+                    return Ok(key_command(key));
+                }
+                Event::Resize(_, _) => return Ok(Command::Unknown),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reads a line typed on the status bar. Escape gives up on the line.
+    fn prompt(&mut self) -> std::io::Result<Option<String>> {
+        let mut line = String::new();
+
+        loop {
+            let (_, height) = terminal::size()?;
+            queue!(
+                self.out,
+                cursor::MoveTo(0, height.saturating_sub(1)),
+                terminal::Clear(ClearType::CurrentLine),
+                Print(format!(":{line}")),
+            )?;
+            self.out.flush()?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Enter => return Ok(Some(line)),
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Backspace => {
+                        line.pop();
+                    }
+                    KeyCode::Char(c) => line.push(c),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+impl Frontend for Terminal {
+    fn show(&mut self, view: &View) -> std::io::Result<()> {
+        let text = format!("{}{}", view.feedback, view.text);
+        let images = view.images();
+
+        self.draw(
+            &view.deck, view.state, &text, &images, view.count, view.total,
+        )
+    }
+
+    fn read(&mut self, deadline: Option<Instant>) -> std::io::Result<Command> {
+        self.read_command(deadline)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Whole seconds left, rounded up so the count never shows zero early.
+pub fn seconds_left(left: Duration) -> u64 {
+    left.as_millis().div_ceil(1000) as u64
+}
+
+fn to_u16(value: usize) -> u16 {
+    u16::try_from(value).unwrap_or(u16::MAX)
+}
+
+/// Maps a key press onto a command. Letters and digits follow the line
+/// mode's single-character commands, with a few extra keys for comfort.
+pub fn key_command(key: KeyEvent) -> Command {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return Command::Quit;
+    }
+
+    match key.code {
+        KeyCode::Left => Command::Previous,
+        KeyCode::Right => Command::Next,
+        KeyCode::Char(' ') | KeyCode::Enter => Command::Flip,
+        KeyCode::Esc => Command::Quit,
+        KeyCode::Char(c) => parse_command(&c.to_string()),
+        _ => Command::Unknown,
+    }
+}
+
+/// Word-wraps `text` to `width` columns, keeping explicit line breaks and
+/// splitting words that are longer than a whole line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+
+            while word.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                lines.push(word.drain(..width).collect());
+            }
+
+            let line_len = line.chars().count();
+            if line_len > 0 && line_len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// A bar like `[#####-----] 5/10` whose inner part is `width` columns wide.
+pub fn progress_bar(count: usize, total: usize, width: usize) -> String {
+    let filled = (count * width).checked_div(total).unwrap_or(0).min(width);
+
+    format!(
+        "[{}{}] {count}/{total}",
+        "#".repeat(filled),
+        "-".repeat(width - filled)
+    )
+}
+
+fn status_bar(deck: &str, state: &str, width: usize) -> String {
+    let keys = if state == "back" {
+        "(1) again (2) hard (3) good (4) easy  (p)rev (n)ext (f)lip (s)huffle (o)rder (q)uit"
+    } else {
+        "(p)rev (n)ext (f)lip (h)int (s)huffle (o)rder (:)edit (q)uit"
+    };
+    let status = format!(" {deck} | {state} | {keys}");
+
+    format!("{status:<width$}").chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::Grade;
+
+    #[test]
+    fn test_key_command() {
+        let cases = vec![
+            (KeyEvent::from(KeyCode::Char('n')), Command::Next),
+            (KeyEvent::from(KeyCode::Right), Command::Next),
+            (KeyEvent::from(KeyCode::Char('p')), Command::Previous),
+            (KeyEvent::from(KeyCode::Left), Command::Previous),
+            (KeyEvent::from(KeyCode::Char('f')), Command::Flip),
+            (KeyEvent::from(KeyCode::Char(' ')), Command::Flip),
+            (KeyEvent::from(KeyCode::Char('s')), Command::Shuffle),
+            (KeyEvent::from(KeyCode::Char('q')), Command::Quit),
+            (KeyEvent::from(KeyCode::Esc), Command::Quit),
+            (
+                KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                Command::Quit,
+            ),
+            (
+                KeyEvent::from(KeyCode::Char('3')),
+                Command::Grade(Grade::Good),
+            ),
+            (KeyEvent::from(KeyCode::Char('x')), Command::Unknown),
+            (KeyEvent::from(KeyCode::Tab), Command::Unknown),
+        ];
+
+        for (index, (key, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(key_command(key), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_seconds_left() {
+        let cases = vec![(10_000, 10), (9_001, 10), (9_000, 9), (1, 1), (0, 0)];
+
+        for (index, (millis, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                seconds_left(Duration::from_millis(millis)),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        let cases = vec![
+            ("short", 10, vec!["short"]),
+            ("the quick brown fox", 10, vec!["the quick", "brown fox"]),
+            ("abcdefghijkl", 5, vec!["abcde", "fghij", "kl"]),
+            ("one\ntwo", 10, vec!["one", "two"]),
+            ("a  b   c", 10, vec!["a b c"]),
+        ];
+
+        for (index, (text, width, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(wrap(text, width), expected, "Case {index} failed");
+        }
+    }
+
+    #[test]
+    fn test_progress_bar() {
+        let cases = vec![
+            (1, 10, 10, "[#---------] 1/10"),
+            (5, 10, 10, "[#####-----] 5/10"),
+            (10, 10, 10, "[##########] 10/10"),
+            (1, 3, 6, "[##----] 1/3"),
+            (0, 0, 4, "[----] 0/0"),
+        ];
+
+        for (index, (count, total, width, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                progress_bar(count, total, width),
+                expected,
+                "Case {index} failed"
+            );
+        }
+    }
+}